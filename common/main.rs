@@ -1,4 +1,4 @@
-#[cfg(not(feature = "wasm"))]
+#[cfg(any(feature = "sdl", feature = "term"))]
 fn get_timer() -> impl FnMut() -> f32 {
     use std::thread;
     use std::time::{Duration, Instant};
@@ -80,7 +80,8 @@ fn main() {
     let sdl_context = sdl2::init().unwrap();
     let mut canvas = create_canvase(&sdl_context);
 
-    let mut buffer = [0u32; WIDTH as usize * HEIGHT as usize];
+    // sdl2 has its own Canvas
+    let mut buffer = olive_rs::canvas::Canvas::new(WIDTH, HEIGHT);
     let mut timer = get_timer();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
@@ -100,58 +101,175 @@ fn main() {
             }
         }
 
-        render(&mut buffer, dt);
+        render(buffer.pixels_mut(), dt);
 
         // show on screen
-        show(&buffer, &mut canvas);
+        show(buffer.pixels(), &mut canvas);
     }
 }
 
+// presents with half blocks, or with full resolution graphics: [blocks|sixel|kitty]
 #[cfg(feature = "term")]
 fn main() {
     init();
-    #[macro_export]
-    macro_rules! static_assert {
-        ($cond:expr $(,)?) => {
-            const _: () = assert!($cond,);
-        };
+    use olive_rs::canvas::Canvas;
+    use olive_rs::term::{write_half_blocks, write_kitty, write_sixel, TermColors};
+    use std::io::Write;
+    const _: () = assert!(HEIGHT.is_multiple_of(SCALE_DOWN_FACTOR));
+    const _: () = assert!(WIDTH.is_multiple_of(SCALE_DOWN_FACTOR));
+    // a cell is about twice as tall as it is wide and holds two pixels,
+    // so the image keeps its aspect ratio
+    const LINES: u32 = HEIGHT / SCALE_DOWN_FACTOR;
+    const COLS: u32 = WIDTH * 2 / SCALE_DOWN_FACTOR;
+    let presenter = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "blocks".to_string());
+    let colors = TermColors::detect();
+    let mut stdout = std::io::stdout().lock();
+    if presenter != "blocks" {
+        // graphics are drawn from the top left of a cleared screen every frame
+        write!(stdout, "\x1b[2J").unwrap();
     }
-    static_assert!(HEIGHT % SCALE_DOWN_FACTOR == 0);
-    static_assert!(WIDTH % SCALE_DOWN_FACTOR == 0);
-    const ROWS: u32 = HEIGHT / SCALE_DOWN_FACTOR;
-    const COLS: u32 = WIDTH / SCALE_DOWN_FACTOR;
-    fn show(buffer: &[u32]) {
-        for r in 0..ROWS {
-            let r = r * HEIGHT / ROWS;
-            for c in 0..COLS {
-                let c = c * WIDTH / COLS;
-                print!("{}", color2char(buffer[(r * WIDTH + c) as usize]));
-                print!("{}", color2char(buffer[(r * WIDTH + c) as usize]));
-            }
-            println!();
-        }
-        print!("\x1b[{ROWS}A");
-        print!("\x1b[{COLS}D");
-    }
-    fn color2char(color: u32) -> char {
-        #[allow(non_upper_case_globals)]
-        const table: &str = " .:a@#";
-        let r = color & 0x000000ff;
-        let g = (color & 0x0000ff00) >> 8;
-        let b = (color & 0x00ff0000) >> (8 * 2);
-        let brightness = ((r + r + r + b + g + g + g + g) >> 3) as usize;
-        let i = brightness * table.len() / 256;
-        table.as_bytes()[i] as char
-    }
-    let mut buffer = [0u32; WIDTH as usize * HEIGHT as usize];
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
     let mut timer = get_timer();
     loop {
         // handle time, fps cap
         let dt = timer();
 
-        render(&mut buffer, dt);
+        render(canvas.pixels_mut(), dt);
 
-        // show on screen
-        show(&buffer);
+        // show on screen, then go back to the top left for the next frame
+        let renderer = canvas.renderer();
+        match presenter.as_str() {
+            "blocks" => {
+                write_half_blocks(&mut stdout, &renderer, COLS, LINES, colors).unwrap();
+                write!(stdout, "\x1b[{LINES}A\x1b[{COLS}D").unwrap();
+            }
+            "sixel" => {
+                write!(stdout, "\x1b[H").unwrap();
+                write_sixel(&mut stdout, &renderer).unwrap();
+            }
+            // the same id replaces the previous frame instead of stacking images
+            "kitty" => {
+                write!(stdout, "\x1b[H").unwrap();
+                write_kitty(&mut stdout, &renderer, Some(1)).unwrap();
+            }
+            _ => panic!("unknown presenter {presenter}, expected blocks, sixel or kitty"),
+        }
+    }
+}
+
+// records frames without a display: [output.gif] [frame count]
+#[cfg(feature = "gif")]
+fn main() {
+    use olive_rs::canvas::Canvas;
+    use olive_rs::image::gif::GifRecorder;
+    use std::{fs::File, io::BufWriter};
+    // gif delays are in hundredths of a second, so 50 fps keeps every frame equally long
+    const DT: f32 = 1.0 / 50.0;
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| {
+        let exe = std::env::current_exe().unwrap();
+        format!("{}.gif", exe.file_stem().unwrap().to_string_lossy())
+    });
+    let frames: u32 = args
+        .next()
+        .map(|n| n.parse().expect("the frame count should be a number"))
+        .unwrap_or(100);
+
+    init();
+    let file = BufWriter::new(File::create(&path).unwrap());
+    let mut recorder = GifRecorder::new(file, WIDTH, HEIGHT).unwrap();
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    for _ in 0..frames {
+        render(canvas.pixels_mut(), DT);
+        recorder.add_frame(&canvas.renderer(), DT).unwrap();
+    }
+    recorder.finish().unwrap();
+    println!("recorded {frames} frames to {path}");
+}
+
+// renders a fixed number of frames without a display: [output] [frame count] [--last]
+// every frame is written next to the output with its number appended, unless --last is given,
+// the format comes from the output extension
+#[cfg(feature = "headless")]
+fn main() {
+    use olive_rs::canvas::Canvas;
+    use olive_rs::image::ImageFormat;
+    use std::path::Path;
+    // a fixed step, so every run renders the same frames
+    const DT: f32 = 1.0 / 60.0;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let last_only = args.iter().any(|arg| arg == "--last");
+    args.retain(|arg| arg != "--last");
+    let mut args = args.into_iter();
+    let path = args.next().unwrap_or_else(|| {
+        let exe = std::env::current_exe().unwrap();
+        format!("{}.png", exe.file_stem().unwrap().to_string_lossy())
+    });
+    let frames: u32 = args
+        .next()
+        .map(|n| n.parse().expect("the frame count should be a number"))
+        .unwrap_or(60);
+    let path = Path::new(&path);
+    let format = ImageFormat::from_path(path)
+        .expect("the output should be a ppm, png, bmp, tga, qoi or ff file");
+    let frame_path = |frame: u32| {
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        let extension = path.extension().unwrap().to_string_lossy();
+        path.with_file_name(format!("{stem}_{frame:04}.{extension}"))
+    };
+
+    init();
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    for frame in 0..frames {
+        render(canvas.pixels_mut(), DT);
+        if !last_only {
+            let renderer = canvas.renderer();
+            renderer.save_to_file(frame_path(frame), format).unwrap();
+        }
+    }
+    if last_only {
+        let renderer = canvas.renderer();
+        renderer.save_to_file(path, format).unwrap();
+        println!(
+            "rendered {frames} frames, saved the last one to {}",
+            path.display()
+        );
+    } else {
+        println!("rendered {frames} frames to {}", frame_path(0).display());
+    }
+}
+
+// for wasm32-unknown-unknown, the page calls init once, then render every frame
+// and draws WIDTH x HEIGHT pixels from the returned pointer into an ImageData
+#[cfg(feature = "wasm")]
+mod wasm {
+    use super::{HEIGHT, WIDTH};
+
+    static mut BUFFER: [u32; WIDTH as usize * HEIGHT as usize] =
+        [0; WIDTH as usize * HEIGHT as usize];
+
+    #[no_mangle]
+    pub extern "C" fn width() -> u32 {
+        WIDTH
+    }
+    #[no_mangle]
+    pub extern "C" fn height() -> u32 {
+        HEIGHT
+    }
+    #[no_mangle]
+    pub extern "C" fn init() {
+        super::init();
+    }
+    // 0xAABBGGRR pixels are RGBA bytes in little endian memory, what ImageData expects
+    #[no_mangle]
+    pub extern "C" fn render(dt: f32) -> *const u32 {
+        let buffer = unsafe { &mut *std::ptr::addr_of_mut!(BUFFER) };
+        super::render(buffer, dt);
+        buffer.as_ptr()
     }
 }
+
+#[cfg(feature = "wasm")]
+fn main() {}
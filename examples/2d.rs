@@ -1,5 +1,6 @@
 use core::f32;
 
+use olive_rs::color::Color;
 use olive_rs::renderer::Renderer;
 
 #[cfg(feature = "term")]
@@ -13,15 +14,10 @@ static mut BULL_X_SPEED: f32 = 200f32;
 static mut BALL_Y: f32 = (HEIGHT / 2) as f32;
 static mut BULL_Y_SPEED: f32 = 200f32;
 
-const WHITE: u32 = 0xff_ffffff;
-const RED: u32 = 0xff_0000ff;
-const GREEN: u32 = 0xff_00ff00;
-const BLUE: u32 = 0xff_ff0000;
-
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 
-const BACKGROUND_COLOR: u32 = 0xFF_202020;
+const BACKGROUND_COLOR: Color = Color::rgb(0x20, 0x20, 0x20);
 
 fn rotate_point(x: &mut i32, y: &mut i32, angle: f32) {
     let dx = *x - WIDTH as i32 / 2;
@@ -57,13 +53,28 @@ pub fn render(buffer: &mut [u32], dt: f32) {
         rotate_point(&mut x1, &mut y1, ANGLE);
         rotate_point(&mut x2, &mut y2, ANGLE);
 
-        renderer.fill_triangle_mix(x0, y0, RED, x1, y1, GREEN, x2, y2, BLUE);
-        renderer.fill_text("R", x0, y0, 4, WHITE);
-        renderer.fill_text("G", x1, y1, 4, WHITE);
-        renderer.fill_text("B", x2, y2, 4, WHITE);
+        renderer.fill_triangle_mix(
+            x0,
+            y0,
+            Color::RED,
+            x1,
+            y1,
+            Color::GREEN,
+            x2,
+            y2,
+            Color::BLUE,
+        );
+        renderer.fill_text("R", x0, y0, 4, Color::WHITE);
+        renderer.fill_text("G", x1, y1, 4, Color::WHITE);
+        renderer.fill_text("B", x2, y2, 4, Color::WHITE);
 
         renderer.begin_blending();
-        renderer.fill_circle_aa(BALL_X as i32, BALL_Y as i32, BALL_R, 0x6900ff00);
+        renderer.fill_circle_aa(
+            BALL_X as i32,
+            BALL_Y as i32,
+            BALL_R,
+            Color::GREEN.with_alpha(0x69),
+        );
         renderer.end_blending();
     }
 }
@@ -1,4 +1,5 @@
 use core::f32;
+use olive_rs::color::Color;
 use olive_rs::renderer::Renderer;
 
 #[cfg(feature = "term")]
@@ -24,7 +25,7 @@ pub fn render(buffer: &mut [u32], dt: f32) {
         angle += 0.25 * dt * f32::consts::PI;
 
         // The rest of the game loop goes here...
-        renderer.fill(0xff202020);
+        renderer.fill(Color::rgb(0x20, 0x20, 0x20));
         for iy in 0..GRID_COUNT {
             let y = -GRID_SIZE / 2f32 + (iy as f32 * GRID_PADDING);
             for ix in 0..GRID_COUNT {
@@ -52,7 +53,7 @@ pub fn render(buffer: &mut [u32], dt: f32) {
                     let g = iy * 255 / GRID_COUNT;
                     let b = iz * 255 / GRID_COUNT;
 
-                    let color: u32 = 0xFF000000 | (r) | (g << (8)) | (b << (2 * 8));
+                    let color = Color::rgb(r as u8, g as u8, b as u8);
 
                     renderer.fill_circle(
                         ((x + 1f32) / 2f32 * WIDTH as f32) as i32,
@@ -1,6 +1,7 @@
 use std::f32;
 use std::f32::consts::PI;
 
+use olive_rs::color::Color;
 use olive_rs::renderer::Renderer;
 
 #[cfg(feature = "term")]
@@ -9,11 +10,6 @@ const SCALE_DOWN_FACTOR: u32 = 20;
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 
-const RED: u32 = 0xff0000ff;
-const GREEN: u32 = 0xff00ff00;
-const BLUE: u32 = 0xffff0000;
-const WHITE: u32 = 0xffffffff;
-
 struct Vector2 {
     x: f32,
     y: f32,
@@ -53,7 +49,7 @@ pub fn render(buffer: &mut [u32], dt: f32) {
         GLOBAL_TIME += dt;
 
         let mut renderer = Renderer::new(buffer, WIDTH, HEIGHT);
-        renderer.fill(0xff181818);
+        renderer.fill(Color::rgb(0x18, 0x18, 0x18));
 
         let z = 1.5f32;
 
@@ -73,13 +69,13 @@ pub fn render(buffer: &mut [u32], dt: f32) {
             renderer.fill_triangle_mix(
                 p0.x as i32,
                 p0.y as i32,
-                RED,
+                Color::RED,
                 p1.x as i32,
                 p1.y as i32,
-                GREEN,
+                Color::GREEN,
                 p2.x as i32,
                 p2.y as i32,
-                BLUE,
+                Color::BLUE,
             );
         }
 
@@ -99,13 +95,13 @@ pub fn render(buffer: &mut [u32], dt: f32) {
             renderer.fill_triangle_mix(
                 p0.x as i32,
                 p0.y as i32,
-                RED,
+                Color::RED,
                 p1.x as i32,
                 p1.y as i32,
-                GREEN,
+                Color::GREEN,
                 p2.x as i32,
                 p2.y as i32,
-                BLUE,
+                Color::BLUE,
             );
         }
     }
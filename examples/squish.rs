@@ -1,17 +1,19 @@
 use core::f32;
 
-use image::{ImageBuffer, Rgba};
+use std::sync::OnceLock;
+
+use olive_rs::canvas::Canvas;
+use olive_rs::color::Color;
+use olive_rs::image::png;
 use olive_rs::renderer::Renderer;
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 
 const IMAGE_DATA: &[u8] = include_bytes!("../assets/tsodinPog.png");
-static mut IMAGE: Vec<u32> = Vec::new();
-static mut IMAGE_WIDTH: u32 = 0;
-static mut IMAGE_HEIGHT: u32 = 0;
+static IMAGE: OnceLock<Canvas> = OnceLock::new();
 
-const BACKGROUND_COLOR: u32 = 0xFF_202020;
+const BACKGROUND_COLOR: Color = Color::rgb(0x20, 0x20, 0x20);
 
 #[cfg(feature = "term")]
 const SCALE_DOWN_FACTOR: u32 = 20;
@@ -25,10 +27,6 @@ pub fn render(buffer: &mut [u32], dt: f32) {
     };
 
     let mut renderer = Renderer::new(buffer, WIDTH, HEIGHT);
-    let image = unsafe {
-        let a: &mut [u32] = IMAGE.as_mut();
-        Renderer::new(a, IMAGE_WIDTH, IMAGE_HEIGHT)
-    };
     renderer.fill(BACKGROUND_COLOR);
     let width = (400 - (d * 80f32) as i32) as u32;
     let height = (400 + (d * 80f32) as i32) as u32;
@@ -43,28 +41,12 @@ pub fn render(buffer: &mut [u32], dt: f32) {
         WIDTH,
         HEIGHT,
     );
-    sub_canvas.copy(&image);
+    sub_canvas.copy_canvas(IMAGE.get().unwrap());
 }
 
 pub fn init() {
-    let img = image::load_from_memory_with_format(IMAGE_DATA, image::ImageFormat::Png).unwrap();
-    let img: ImageBuffer<Rgba<u8>, Vec<u8>> = img.to_rgba8();
-
-    let width = img.width();
-    let height = img.height();
-
-    let rgba32: Vec<u32> = img
-        .pixels()
-        .map(|pixel| {
-            let [r, g, b, a] = pixel.0;
-            (a as u32) << 24 | (b as u32) << 16 | (g as u32) << 8 | (r as u32)
-        })
-        .collect();
-    unsafe {
-        IMAGE = rgba32;
-        IMAGE_WIDTH = width;
-        IMAGE_HEIGHT = height;
-    }
+    let image = png::decode(IMAGE_DATA).unwrap();
+    IMAGE.get_or_init(|| image.into());
 }
 
 include!("../common/main.rs");
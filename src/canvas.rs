@@ -0,0 +1,103 @@
+// a canvas that owns its pixels, drawn to through the renderer it lends
+use alloc::{vec, vec::Vec};
+
+use crate::color::Color;
+use crate::pixel_format::{Abgr8888, PixelFormat};
+use crate::renderer::{Pattern, Renderer};
+
+#[derive(Clone)]
+pub struct Canvas<F: PixelFormat = Abgr8888> {
+    pixels: Vec<F::Pixel>,
+    width: u32,
+    height: u32,
+    format: F,
+}
+impl Canvas {
+    // transparent black
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, Abgr8888)
+    }
+    pub fn pattern(&self) -> Pattern<'_> {
+        Pattern::new(&self.pixels, self.width, self.height)
+    }
+}
+impl<F: PixelFormat> Canvas<F> {
+    pub fn with_format(width: u32, height: u32, format: F) -> Self {
+        Self {
+            pixels: vec![F::from_rgba(0); width as usize * height as usize],
+            width,
+            height,
+            format,
+        }
+    }
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<F::Pixel>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        Self {
+            pixels,
+            width,
+            height,
+            format: F::default(),
+        }
+    }
+    pub fn into_pixels(self) -> Vec<F::Pixel> {
+        self.pixels
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn pixels(&self) -> &[F::Pixel] {
+        &self.pixels
+    }
+    pub fn pixels_mut(&mut self) -> &mut [F::Pixel] {
+        &mut self.pixels
+    }
+    pub fn renderer(&mut self) -> Renderer<'_, F> {
+        Renderer::with_format(&mut self.pixels, self.width, self.height, self.format)
+    }
+    // None outside of the canvas
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let pixel = self.pixels[(y * self.width + x) as usize];
+        Some(Color::from_u32(F::to_rgba(pixel)))
+    }
+    // replaces the pixel without blending, nothing happens outside of the canvas
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: impl Into<Color>) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.pixels[(y * self.width + x) as usize] = F::from_rgba(color.into().to_u32());
+    }
+    // what was in the top left corner stays in place, new pixels are transparent black
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        let mut pixels = vec![F::from_rgba(0); width as usize * height as usize];
+        let (w, h) = (
+            self.width.min(width) as usize,
+            self.height.min(height) as usize,
+        );
+        if w > 0 {
+            for (new_row, row) in pixels
+                .chunks_exact_mut(width as usize)
+                .zip(self.pixels.chunks_exact(self.width as usize))
+                .take(h)
+            {
+                new_row[..w].copy_from_slice(&row[..w]);
+            }
+        }
+        self.pixels = pixels;
+        self.width = width;
+        self.height = height;
+    }
+}
+impl<'a, F: PixelFormat> From<&'a mut Canvas<F>> for Renderer<'a, F> {
+    fn from(canvas: &'a mut Canvas<F>) -> Self {
+        canvas.renderer()
+    }
+}
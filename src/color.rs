@@ -0,0 +1,185 @@
+// an RGBA color, converts losslessly to and from the 0xAABBGGRR pixels of the buffer
+#[cfg(not(feature = "std"))]
+use crate::math::FloatExt;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(0xff, 0xff, 0xff);
+    pub const GRAY: Color = Color::rgb(0x80, 0x80, 0x80);
+    pub const RED: Color = Color::rgb(0xff, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 0xff, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 0xff);
+    pub const YELLOW: Color = Color::rgb(0xff, 0xff, 0);
+    pub const CYAN: Color = Color::rgb(0, 0xff, 0xff);
+    pub const MAGENTA: Color = Color::rgb(0xff, 0, 0xff);
+    pub const ORANGE: Color = Color::rgb(0xff, 0xa5, 0);
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 0xff)
+    }
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+    // a pixel in the 0xAABBGGRR layout of the buffer
+    pub const fn from_u32(pixel: u32) -> Self {
+        let [r, g, b, a] = pixel.to_le_bytes();
+        Self { r, g, b, a }
+    }
+    pub const fn to_u32(self) -> u32 {
+        u32::from_le_bytes([self.r, self.g, self.b, self.a])
+    }
+    // "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa", the # is optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // from_str_radix would also take a sign
+        if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 | 4 => {
+                // every digit is doubled, f is ff
+                let mut channels = [0xff; 4];
+                for (i, channel) in channels.iter_mut().take(hex.len()).enumerate() {
+                    *channel = digit(i)? * 0x11;
+                }
+                let [r, g, b, a] = channels;
+                Some(Self::rgba(r, g, b, a))
+            }
+            6 => Some(Self::rgb(byte(0)?, byte(2)?, byte(4)?)),
+            8 => Some(Self::rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+            _ => None,
+        }
+    }
+    // hue in degrees, saturation and value from 0 to 1, opaque
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        let c = v * s;
+        Self::from_hue(h, c, v - c)
+    }
+    // hue in degrees, saturation and lightness from 0 to 1, opaque
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue(h, c, l - c / 2.0)
+    }
+    // chroma c spread over the channels by the hue, plus m on every channel
+    fn from_hue(h: f32, c: f32, m: f32) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let to_u8 = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Self::rgb(to_u8(r), to_u8(g), to_u8(b))
+    }
+    // hue in degrees from 0 to 360, saturation and value from 0 to 1, alpha is dropped
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (h, s, max)
+    }
+    // hue in degrees from 0 to 360, saturation and lightness from 0 to 1, alpha is dropped
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        (h, s, l)
+    }
+    // the hue, and the largest and smallest channel from 0 to 1
+    fn hue(self) -> (f32, f32, f32) {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| c as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        (h, max, min)
+    }
+    // every channel, alpha included, goes from self at t = 0 to other at t = 1
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Self::rgba(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+}
+impl From<u32> for Color {
+    fn from(pixel: u32) -> Self {
+        Self::from_u32(pixel)
+    }
+}
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        color.to_u32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex() {
+        assert_eq!(Color::from_hex("#f80"), Some(Color::rgb(0xff, 0x88, 0)));
+        assert_eq!(
+            Color::from_hex("f808"),
+            Some(Color::rgba(0xff, 0x88, 0, 0x88))
+        );
+        assert_eq!(
+            Color::from_hex("#12aBcD"),
+            Some(Color::rgb(0x12, 0xab, 0xcd))
+        );
+        assert_eq!(
+            Color::from_hex("12abcd80"),
+            Some(Color::rgba(0x12, 0xab, 0xcd, 0x80))
+        );
+        for hex in [
+            "",
+            "#",
+            "#ff",
+            "#fffff",
+            "#gggggg",
+            "#f\u{e9}f",
+            "#+f0",
+            "+fff",
+            "#+f00ff00",
+            "-fff",
+        ] {
+            assert_eq!(Color::from_hex(hex), None, "{hex}");
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fmt, fs,
+    io::{self, BufWriter, Write},
+};
+
+use crate::canvas::Canvas;
+use crate::renderer::{Pattern, Renderer};
+
+pub mod bmp;
+pub mod farbfeld;
+pub mod gif;
+pub mod png;
+pub mod ppm;
+pub mod qoi;
+pub mod tga;
+pub(crate) mod zlib;
+
+// an owned image, in the same 0xAABBGGRR layout the renderer uses
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        }
+    }
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+    pub fn renderer(&mut self) -> Renderer<'_> {
+        Renderer::new(&mut self.pixels, self.width, self.height)
+    }
+    pub fn pattern(&self) -> Pattern<'_> {
+        Pattern::new(&self.pixels, self.width, self.height)
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[u32]> + Clone + '_ {
+        self.pixels.chunks_exact(self.width.max(1) as usize)
+    }
+    pub fn write_image(&self, writer: impl Write, format: ImageFormat) -> io::Result<()> {
+        write_rows(writer, self.width, self.height, self.rows(), format)
+    }
+    pub fn save_to_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        format: ImageFormat,
+    ) -> io::Result<()> {
+        self.write_image(BufWriter::new(fs::File::create(path)?), format)
+    }
+    pub fn load_bmp_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        bmp::decode(&fs::read(path)?)
+    }
+    pub fn load_tga_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        tga::decode(&fs::read(path)?)
+    }
+    pub fn load_qoi_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        qoi::decode(&fs::read(path)?)
+    }
+    pub fn load_farbfeld_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        farbfeld::decode(&fs::read(path)?)
+    }
+    pub fn load_png_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        png::decode(&fs::read(path)?)
+    }
+    pub fn load_ppm_file(path: impl AsRef<std::path::Path>) -> Result<Self, ImageError> {
+        ppm::decode(&fs::read(path)?)
+    }
+}
+impl From<Image> for Canvas {
+    fn from(image: Image) -> Self {
+        Canvas::from_pixels(image.width, image.height, image.pixels)
+    }
+}
+impl From<Canvas> for Image {
+    fn from(canvas: Canvas) -> Self {
+        let (width, height) = (canvas.width(), canvas.height());
+        Image::from_pixels(width, height, canvas.into_pixels())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    // binary P6, without alpha
+    Ppm,
+    Png,
+    Bmp,
+    Tga { rle: bool },
+    Qoi,
+    Farbfeld,
+}
+impl ImageFormat {
+    // guessed from the extension, tga files are run length encoded
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "bmp" => Some(ImageFormat::Bmp),
+            "tga" => Some(ImageFormat::Tga { rle: true }),
+            "qoi" => Some(ImageFormat::Qoi),
+            "ff" => Some(ImageFormat::Farbfeld),
+            _ => None,
+        }
+    }
+}
+
+// every exporter goes through rows of width pixels,
+// so buffers with a stride, like sub canvases, are written correctly
+pub fn write_rows<'a>(
+    writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+    format: ImageFormat,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Ppm => ppm::write(writer, width, height, rows),
+        ImageFormat::Png => png::write(writer, width, height, rows),
+        ImageFormat::Bmp => bmp::write(writer, width, height, rows),
+        ImageFormat::Tga { rle } => tga::write(writer, width, height, rows, rle),
+        ImageFormat::Qoi => qoi::write(writer, width, height, rows),
+        ImageFormat::Farbfeld => farbfeld::write(writer, width, height, rows),
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    // the data does not start with the signature of the format
+    InvalidSignature,
+    InvalidHeader(String),
+    // a valid file using a feature that is not supported
+    Unsupported(String),
+    InvalidData(String),
+    UnexpectedEof,
+}
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "io error: {e}"),
+            ImageError::InvalidSignature => write!(f, "invalid signature"),
+            ImageError::InvalidHeader(msg) => write!(f, "invalid header: {msg}"),
+            ImageError::Unsupported(msg) => write!(f, "unsupported: {msg}"),
+            ImageError::InvalidData(msg) => write!(f, "invalid data: {msg}"),
+            ImageError::UnexpectedEof => write!(f, "unexpected end of data"),
+        }
+    }
+}
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+// a small image for the codec tests, with an odd width, alpha and runs longer than 62 pixels
+#[cfg(test)]
+pub(crate) fn test_image() -> Image {
+    use crate::renderer::rgba2color;
+    let (width, height) = (67, 5);
+    let pixels = (0..height)
+        .flat_map(|y| {
+            (0..width).map(move |x| match y {
+                0 => 0xff_336699,
+                1 => rgba2color(x * 3, 0xff - x * 3, x, 0x80 + x),
+                2 if x % 2 == 0 => 0xff_ffffff,
+                2 => 0x40_102030,
+                3 => rgba2color(100 + x % 4, 100 + x / 8, 100, 0xff),
+                _ => 0,
+            })
+        })
+        .collect();
+    Image::from_pixels(width, height, pixels)
+}
+
+// the test image without alpha, for formats that drop it
+#[cfg(test)]
+pub(crate) fn test_image_opaque() -> Image {
+    let mut image = test_image();
+    image
+        .pixels
+        .iter_mut()
+        .for_each(|pixel| *pixel |= 0xff_000000);
+    image
+}
@@ -0,0 +1,230 @@
+// BMP reader for 24 and 32 bit images, and writer,
+// 24 bit when every pixel is opaque, 32 bit with an alpha mask otherwise
+use std::io::{self, Read, Write};
+
+use super::{Image, ImageError};
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+const FILE_HEADER_LEN: u32 = 14;
+const INFO_HEADER_LEN: u32 = 40;
+const V4_HEADER_LEN: u32 = 108;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+// rows are width pixels long, in the 0xAABBGGRR layout
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+) -> io::Result<()> {
+    let rows: Vec<&[u32]> = rows.take(height as usize).collect();
+    let opaque = rows
+        .iter()
+        .all(|row| row.iter().all(|&pixel| alpha_part(pixel) == 0xff));
+    let (bits, header_len) = if opaque {
+        (24, INFO_HEADER_LEN)
+    } else {
+        (32, V4_HEADER_LEN)
+    };
+    // rows are padded to 4 bytes
+    let row_len = (width as usize * bits / 8).next_multiple_of(4);
+    let offset = FILE_HEADER_LEN + header_len;
+    let file_len = offset as usize + row_len * height as usize;
+
+    let mut header = Vec::with_capacity(offset as usize);
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(file_len as u32).to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&offset.to_le_bytes());
+    header.extend_from_slice(&header_len.to_le_bytes());
+    header.extend_from_slice(&(width as i32).to_le_bytes());
+    // positive height, the rows are stored bottom up
+    header.extend_from_slice(&(height as i32).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&(bits as u16).to_le_bytes());
+    let compression = if opaque { BI_RGB } else { BI_BITFIELDS };
+    header.extend_from_slice(&compression.to_le_bytes());
+    header.extend_from_slice(&((row_len * height as usize) as u32).to_le_bytes());
+    // 72 dpi
+    header.extend_from_slice(&2835u32.to_le_bytes());
+    header.extend_from_slice(&2835u32.to_le_bytes());
+    header.extend_from_slice(&[0; 8]);
+    if !opaque {
+        for mask in [0x00ff0000u32, 0x0000ff00, 0x000000ff, 0xff000000] {
+            header.extend_from_slice(&mask.to_le_bytes());
+        }
+        header.extend_from_slice(b"BGRs");
+        // endpoints and gamma, unused with the sRGB color space
+        header.extend_from_slice(&[0; 48]);
+    }
+    writer.write_all(&header)?;
+
+    let mut bytes = Vec::with_capacity(row_len);
+    for row in rows.iter().rev() {
+        bytes.clear();
+        for &pixel in &row[..width as usize] {
+            let bgra = [
+                blue_part(pixel) as u8,
+                green_part(pixel) as u8,
+                red_part(pixel) as u8,
+                alpha_part(pixel) as u8,
+            ];
+            bytes.extend_from_slice(&bgra[..bits / 8]);
+        }
+        bytes.resize(row_len, 0);
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(b"BM") {
+        return Err(ImageError::InvalidSignature);
+    }
+    let u16_at = |i: usize| -> Result<u32, ImageError> {
+        let bytes = data.get(i..i + 2).ok_or(ImageError::UnexpectedEof)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as u32)
+    };
+    let u32_at = |i: usize| -> Result<u32, ImageError> {
+        let bytes = data.get(i..i + 4).ok_or(ImageError::UnexpectedEof)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let offset = u32_at(10)? as usize;
+    let header_len = u32_at(14)?;
+    let info = FILE_HEADER_LEN as usize;
+    let (width, height, bits, compression) = match header_len {
+        // BITMAPCOREHEADER
+        12 => (
+            u16_at(info + 4)? as i32,
+            u16_at(info + 6)? as i16 as i32,
+            u16_at(info + 10)?,
+            BI_RGB,
+        ),
+        40 | 52 | 56 | 64 | 108 | 124 => (
+            u32_at(info + 4)? as i32,
+            u32_at(info + 8)? as i32,
+            u16_at(info + 14)?,
+            u32_at(info + 16)?,
+        ),
+        _ => {
+            return Err(ImageError::Unsupported(format!(
+                "header of {header_len} bytes"
+            )))
+        }
+    };
+    if bits != 24 && bits != 32 {
+        return Err(ImageError::Unsupported(format!("{bits} bits per pixel")));
+    }
+    // red, green, blue and alpha masks
+    let masks = match compression {
+        BI_RGB if bits == 24 => [0x00ff0000, 0x0000ff00, 0x000000ff, 0],
+        // the fourth byte is reserved, but some writers store alpha in it, checked below
+        BI_RGB => [0x00ff0000, 0x0000ff00, 0x000000ff, 0xff000000],
+        BI_BITFIELDS | BI_ALPHABITFIELDS if bits == 32 => {
+            // after the BITMAPINFOHEADER, or inside the larger headers
+            let masks_at = info + INFO_HEADER_LEN as usize;
+            let has_alpha = compression == BI_ALPHABITFIELDS || header_len >= 56;
+            [
+                u32_at(masks_at)?,
+                u32_at(masks_at + 4)?,
+                u32_at(masks_at + 8)?,
+                if has_alpha { u32_at(masks_at + 12)? } else { 0 },
+            ]
+        }
+        _ => {
+            return Err(ImageError::Unsupported(format!(
+                "compression {compression} with {bits} bits per pixel"
+            )))
+        }
+    };
+    if width <= 0 || height == 0 || height == i32::MIN {
+        return Err(ImageError::InvalidHeader(format!(
+            "image size {width}x{height}"
+        )));
+    }
+    // a negative height means the rows are stored top down
+    let top_down = height < 0;
+    let (width, height) = (width as u32, height.unsigned_abs());
+    let row_len = (width as usize * bits as usize / 8).next_multiple_of(4);
+    let len = (row_len as u64).checked_mul(height as u64);
+    let raster = match len {
+        Some(len) if offset as u64 + len <= data.len() as u64 => {
+            &data[offset..offset + len as usize]
+        }
+        _ => return Err(ImageError::UnexpectedEof),
+    };
+
+    let mut image = Image::new(width, height);
+    let bytes_per_pixel = bits as usize / 8;
+    for (y, row) in raster.chunks_exact(row_len).enumerate() {
+        let y = if top_down { y } else { height as usize - 1 - y };
+        let pixels = &mut image.pixels[y * width as usize..(y + 1) * width as usize];
+        for (pixel, bytes) in pixels.iter_mut().zip(row.chunks_exact(bytes_per_pixel)) {
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0u32, |value, &byte| (value << 8) | byte as u32);
+            let [r, g, b, a] = masks.map(|mask| channel(value, mask));
+            *pixel = rgba2color(r, g, b, if masks[3] == 0 { 0xff } else { a });
+        }
+    }
+    if compression == BI_RGB && bits == 32 && image.pixels.iter().all(|&p| alpha_part(p) == 0) {
+        image.pixels.iter_mut().for_each(|p| *p |= 0xff000000);
+    }
+    Ok(image)
+}
+
+// the channel under mask, scaled to 8 bits
+fn channel(value: u32, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = (1u64 << mask.count_ones()) - 1;
+    let v = ((value & mask) >> mask.trailing_zeros()) as u64;
+    ((v * 255 + max / 2) / max) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{test_image, test_image_opaque};
+
+    #[test]
+    fn round_trip() {
+        for image in [test_image(), test_image_opaque()] {
+            let mut data = Vec::new();
+            write(&mut data, image.width, image.height, image.rows()).unwrap();
+            assert_eq!(decode(&data).unwrap(), image);
+        }
+    }
+
+    #[test]
+    fn bottom_up_padded_rows() {
+        let image = test_image_opaque();
+        let mut data = Vec::new();
+        write(&mut data, image.width, image.height, image.rows()).unwrap();
+        // 67 pixels of 3 bytes are padded to 204 bytes
+        let offset = (FILE_HEADER_LEN + INFO_HEADER_LEN) as usize;
+        assert_eq!(data.len(), offset + 204 * 5);
+        // the last row comes first
+        let last = image.pixels[4 * 67];
+        assert_eq!(
+            data[offset..offset + 3],
+            [
+                blue_part(last) as u8,
+                green_part(last) as u8,
+                red_part(last) as u8
+            ]
+        );
+        assert!(data[offset + 201..offset + 204].iter().all(|&b| b == 0));
+    }
+}
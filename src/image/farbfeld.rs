@@ -0,0 +1,88 @@
+// farbfeld, 16 bit big endian RGBA, see https://tools.suckless.org/farbfeld/
+use std::io::{self, Read, Write};
+
+use super::{Image, ImageError};
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+const MAGIC: &[u8; 8] = b"farbfeld";
+const HEADER_LEN: usize = 16;
+
+// rows are width pixels long, in the 0xAABBGGRR layout
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&width.to_be_bytes())?;
+    writer.write_all(&height.to_be_bytes())?;
+    let mut bytes = Vec::with_capacity(width as usize * 8);
+    for row in rows.take(height as usize) {
+        bytes.clear();
+        for &pixel in &row[..width as usize] {
+            for channel in [
+                red_part(pixel),
+                green_part(pixel),
+                blue_part(pixel),
+                alpha_part(pixel),
+            ] {
+                // 0xff becomes 0xffff
+                bytes.extend_from_slice(&((channel * 257) as u16).to_be_bytes());
+            }
+        }
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ImageError::InvalidSignature);
+    }
+    if data.len() < HEADER_LEN {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let width = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    let height = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
+    let len = width as u64 * height as u64;
+    match len.checked_mul(8) {
+        Some(bytes) if bytes <= (data.len() - HEADER_LEN) as u64 => {}
+        _ => return Err(ImageError::UnexpectedEof),
+    }
+    let to_u8 =
+        |bytes: &[u8]| (u16::from_be_bytes([bytes[0], bytes[1]]) as u32 * 255 + 32767) / 65535;
+    let pixels = data[HEADER_LEN..HEADER_LEN + len as usize * 8]
+        .chunks_exact(8)
+        .map(|p| {
+            rgba2color(
+                to_u8(&p[0..2]),
+                to_u8(&p[2..4]),
+                to_u8(&p[4..6]),
+                to_u8(&p[6..8]),
+            )
+        })
+        .collect();
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::test_image;
+
+    #[test]
+    fn round_trip() {
+        let image = test_image();
+        let mut data = Vec::new();
+        write(&mut data, image.width, image.height, image.rows()).unwrap();
+        assert_eq!(data.len(), HEADER_LEN + 67 * 5 * 8);
+        assert_eq!(decode(&data).unwrap(), image);
+    }
+}
@@ -0,0 +1,405 @@
+// animated GIF encoder, every frame gets its own palette of up to 256 colors,
+// alpha is ignored
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::renderer::{blue_part, green_part, red_part, Renderer};
+
+const MAX_CODE_SIZE: u32 = 12;
+
+// records successive frames into an animation that loops forever
+pub struct GifRecorder<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    // in seconds, delays are rounded from the total so they don't drift
+    time: f64,
+}
+impl<W: Write> GifRecorder<W> {
+    pub fn new(mut writer: W, width: u32, height: u32) -> io::Result<Self> {
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "GIF images are at most 65535x65535",
+            ));
+        }
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&(width as u16).to_le_bytes())?;
+        writer.write_all(&(height as u16).to_le_bytes())?;
+        // no global color table, 8 bits per primary color, background color, aspect ratio
+        writer.write_all(&[0x70, 0, 0])?;
+        // NETSCAPE2.0 extension, looping forever
+        writer.write_all(&[0x21, 0xff, 11])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[3, 1, 0, 0, 0])?;
+        Ok(Self {
+            writer,
+            width,
+            height,
+            time: 0f64,
+        })
+    }
+    // the canvas must have the size the recorder was created with,
+    // delay is how long the frame is shown, in seconds
+    pub fn add_frame(&mut self, canvas: &Renderer, delay: f32) -> io::Result<()> {
+        assert_eq!((canvas.width, canvas.height), (self.width, self.height));
+        self.add_frame_rows(canvas.rows(), delay)
+    }
+    // rows are width pixels long, in the 0xAABBGGRR layout
+    pub fn add_frame_rows<'a>(
+        &mut self,
+        rows: impl Iterator<Item = &'a [u32]> + Clone,
+        delay: f32,
+    ) -> io::Result<()> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let rows = rows.take(height).map(|row| &row[..width]);
+        let (palette, indices) = quantize(rows);
+
+        // the delay is in hundredths of a second
+        let start = (self.time * 100f64).round();
+        self.time += delay as f64;
+        let delay = ((self.time * 100f64).round() - start).clamp(0f64, u16::MAX as f64) as u16;
+        // graphic control extension, frames are drawn over the previous one without disposal
+        self.writer.write_all(&[0x21, 0xf9, 4, 1 << 2])?;
+        self.writer.write_all(&delay.to_le_bytes())?;
+        self.writer.write_all(&[0, 0])?;
+
+        // the color table size is a power of two, at least 2 entries
+        let bits = (palette.len().max(2) as u32)
+            .next_power_of_two()
+            .trailing_zeros();
+        self.writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.writer.write_all(&(width as u16).to_le_bytes())?;
+        self.writer.write_all(&(height as u16).to_le_bytes())?;
+        self.writer.write_all(&[0x80 | (bits - 1) as u8])?;
+        let mut table = vec![0u8; 3 << bits];
+        for (entry, color) in table.chunks_exact_mut(3).zip(&palette) {
+            entry.copy_from_slice(color);
+        }
+        self.writer.write_all(&table)?;
+
+        // the minimum code size is at least 2
+        let min_code_size = bits.max(2);
+        self.writer.write_all(&[min_code_size as u8])?;
+        let data = lzw_encode(&indices, min_code_size);
+        for block in data.chunks(255) {
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])
+    }
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// a palette of at most 256 colors and the index of every pixel in it,
+// exact when the frame has few enough colors, median cut otherwise
+pub(crate) fn quantize<'a>(
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let rgb = |pixel: u32| {
+        [
+            red_part(pixel) as u8,
+            green_part(pixel) as u8,
+            blue_part(pixel) as u8,
+        ]
+    };
+    let mut exact: HashMap<u32, u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let fits = rows.clone().flatten().all(|&pixel| {
+        let pixel = pixel & 0x00ffffff;
+        if exact.contains_key(&pixel) {
+            return true;
+        }
+        if palette.len() == 256 {
+            return false;
+        }
+        exact.insert(pixel, palette.len() as u8);
+        palette.push(rgb(pixel));
+        true
+    });
+    if fits {
+        let indices = rows
+            .flatten()
+            .map(|&pixel| exact[&(pixel & 0x00ffffff)])
+            .collect();
+        return (palette, indices);
+    }
+
+    // histogram of colors with 5 bits per channel
+    let bucket = |pixel: u32| {
+        let [r, g, b] = rgb(pixel).map(|c| (c >> 3) as usize);
+        (r << 10) | (g << 5) | b
+    };
+    let mut histogram = vec![0u32; 1 << 15];
+    rows.clone()
+        .flatten()
+        .for_each(|&pixel| histogram[bucket(pixel)] += 1);
+    let mut colors: Vec<(usize, u32)> = histogram
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(bucket, &count)| (bucket, count))
+        .collect();
+    let channel = |bucket: usize, c: usize| (bucket >> (10 - 5 * c)) & 0x1f;
+
+    // split the box with the widest channel range at its median until there are 256 boxes
+    let mut boxes = Vec::with_capacity(256);
+    boxes.push(0..colors.len());
+    while boxes.len() < 256 {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.len() > 1)
+            .map(|(i, range)| {
+                let (c, extent) = (0..3)
+                    .map(|c| {
+                        let values = colors[range.clone()].iter().map(|&(b, _)| channel(b, c));
+                        (c, values.clone().max().unwrap() - values.min().unwrap())
+                    })
+                    .max_by_key(|&(_, extent)| extent)
+                    .unwrap();
+                (i, c, extent)
+            })
+            .max_by_key(|&(_, _, extent)| extent);
+        let Some((i, c, _)) = widest else {
+            break;
+        };
+        let range = boxes[i].clone();
+        let colors = &mut colors[range.clone()];
+        colors.sort_unstable_by_key(|&(b, _)| channel(b, c));
+        let total: u64 = colors.iter().map(|&(_, count)| count as u64).sum();
+        let mut sum = 0u64;
+        let median = colors
+            .iter()
+            .position(|&(_, count)| {
+                sum += count as u64;
+                sum * 2 >= total
+            })
+            .unwrap();
+        let split = range.start + (median + 1).min(colors.len() - 1);
+        boxes[i] = range.start..split;
+        boxes.push(split..range.end);
+    }
+
+    // every box becomes the average of its colors
+    let mut palette = Vec::with_capacity(boxes.len());
+    let mut lookup = vec![0u8; 1 << 15];
+    for (i, range) in boxes.iter().enumerate() {
+        let mut sum = [0u64; 3];
+        let mut total = 0u64;
+        for &(bucket, count) in &colors[range.clone()] {
+            for (c, sum) in sum.iter_mut().enumerate() {
+                *sum += (channel(bucket, c) as u64 * 8 + 4) * count as u64;
+            }
+            total += count as u64;
+            lookup[bucket] = i as u8;
+        }
+        palette.push(sum.map(|s| (s / total).min(255) as u8));
+    }
+    let indices = rows.flatten().map(|&pixel| lookup[bucket(pixel)]).collect();
+    (palette, indices)
+}
+
+// variable length codes, packed least significant bit first
+fn lzw_encode(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u32 << min_code_size;
+    let end = clear + 1;
+    let mut out = Vec::new();
+    let (mut bits, mut len) = (0u32, 0u32);
+    let mut emit = |code: u32, size: u32, out: &mut Vec<u8>| {
+        bits |= code << len;
+        len += size;
+        while len >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            len -= 8;
+        }
+    };
+    // (prefix code, index) -> code
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end + 1;
+    emit(clear, code_size, &mut out);
+    let mut prefix = None;
+    for &index in indices {
+        let Some(current) = prefix else {
+            prefix = Some(index as u32);
+            continue;
+        };
+        if let Some(&code) = table.get(&(current, index)) {
+            prefix = Some(code);
+            continue;
+        }
+        emit(current, code_size, &mut out);
+        if next_code < 1 << MAX_CODE_SIZE {
+            table.insert((current, index), next_code);
+            next_code += 1;
+            // the decoder widens its codes one code later than the encoder adds them
+            if next_code > 1 << code_size && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+        } else {
+            emit(clear, code_size, &mut out);
+            table.clear();
+            code_size = min_code_size + 1;
+            next_code = end + 1;
+        }
+        prefix = Some(index as u32);
+    }
+    if let Some(current) = prefix {
+        emit(current, code_size, &mut out);
+    }
+    emit(end, code_size, &mut out);
+    if len > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{test_image_opaque, Image};
+
+    // the colors and delay of every frame, only what the recorder writes is understood
+    fn decode(data: &[u8]) -> Vec<(Vec<u32>, u16)> {
+        assert!(data.starts_with(b"GIF89a"));
+        // no global color table
+        assert_eq!(data[10] & 0x80, 0);
+        let sub_blocks = |pos: &mut usize| {
+            let mut bytes = Vec::new();
+            while data[*pos] != 0 {
+                let len = data[*pos] as usize;
+                bytes.extend_from_slice(&data[*pos + 1..*pos + 1 + len]);
+                *pos += 1 + len;
+            }
+            *pos += 1;
+            bytes
+        };
+        let mut frames = Vec::new();
+        let mut delay = 0;
+        let mut pos = 13;
+        loop {
+            match data[pos] {
+                0x21 => {
+                    let label = data[pos + 1];
+                    pos += 2;
+                    let extension = sub_blocks(&mut pos);
+                    if label == 0xf9 {
+                        delay = u16::from_le_bytes([extension[1], extension[2]]);
+                    }
+                }
+                0x2c => {
+                    let packed = data[pos + 9];
+                    assert_ne!(packed & 0x80, 0);
+                    let table = &data[pos + 10..pos + 10 + (3 << ((packed & 7) + 1))];
+                    pos += 10 + table.len();
+                    let min_code_size = data[pos] as u32;
+                    pos += 1;
+                    let indices = lzw_decode(&sub_blocks(&mut pos), min_code_size);
+                    let pixels = indices
+                        .iter()
+                        .map(|&i| {
+                            let rgb = &table[3 * i as usize..3 * i as usize + 3];
+                            0xff_000000
+                                | (rgb[2] as u32) << 16
+                                | (rgb[1] as u32) << 8
+                                | rgb[0] as u32
+                        })
+                        .collect();
+                    frames.push((pixels, delay));
+                }
+                0x3b => return frames,
+                block => panic!("unexpected block {block:#x}"),
+            }
+        }
+    }
+
+    fn lzw_decode(data: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let reset = || -> Vec<Vec<u8>> { (0..clear + 2).map(|i| vec![i as u8]).collect() };
+        let mut table = reset();
+        let mut code_size = min_code_size + 1;
+        let mut previous: Option<usize> = None;
+        let mut out = Vec::new();
+        let mut bit = 0;
+        loop {
+            let code = (0..code_size as usize)
+                .map(|i| ((data[(bit + i) / 8] >> ((bit + i) % 8)) & 1) as usize)
+                .enumerate()
+                .fold(0, |code, (i, b)| code | b << i);
+            bit += code_size as usize;
+            if code == clear {
+                table = reset();
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+            let entry = match (table.get(code), previous) {
+                (Some(entry), _) => entry.clone(),
+                // the code being defined, the previous entry and its first index
+                (None, Some(previous)) if code == table.len() => {
+                    let mut entry = table[previous].clone();
+                    entry.push(entry[0]);
+                    entry
+                }
+                _ => panic!("invalid code {code}"),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(previous) = previous {
+                if table.len() < 1 << MAX_CODE_SIZE {
+                    let mut new = table[previous].clone();
+                    new.push(entry[0]);
+                    table.push(new);
+                }
+            }
+            if table.len() == 1 << code_size && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+            previous = Some(code);
+        }
+    }
+
+    fn record(frames: &[&Image], delay: f32) -> Vec<u8> {
+        let (width, height) = (frames[0].width, frames[0].height);
+        let mut recorder = GifRecorder::new(Vec::new(), width, height).unwrap();
+        for frame in frames {
+            recorder.add_frame_rows(frame.rows(), delay).unwrap();
+        }
+        recorder.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        // alpha is ignored
+        let image = test_image_opaque();
+        let frames = decode(&record(&[&image, &image], 0.25));
+        assert_eq!(frames, [(image.pixels.clone(), 25), (image.pixels, 25)]);
+    }
+
+    #[test]
+    fn growing_codes() {
+        // enough noise for the codes to grow to 12 bits and the table to be cleared
+        let mut seed = 0x2545f491u32;
+        let pixels = (0..200 * 100)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                0xff_000000 | ((seed % 200) * 0x010203)
+            })
+            .collect();
+        let image = Image::from_pixels(200, 100, pixels);
+        let frames = decode(&record(&[&image], 0.02));
+        assert_eq!(frames, [(image.pixels, 2)]);
+    }
+}
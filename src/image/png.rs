@@ -0,0 +1,478 @@
+// PNG encoder, writing 8 bit RGBA, or RGB when every pixel is opaque,
+// and decoder for every non interlaced PNG
+use std::io::{self, Read, Write};
+
+use super::{zlib, Image, ImageError};
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const COLOR_TYPE_GRAY: u8 = 0;
+const COLOR_TYPE_RGB: u8 = 2;
+const COLOR_TYPE_PALETTE: u8 = 3;
+const COLOR_TYPE_GRAY_ALPHA: u8 = 4;
+const COLOR_TYPE_RGBA: u8 = 6;
+
+// rows are width pixels long, in the 0xAABBGGRR layout
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+) -> io::Result<()> {
+    let opaque = rows
+        .clone()
+        .all(|row| row.iter().all(|&pixel| alpha_part(pixel) == 0xff));
+    let (color_type, channels) = if opaque {
+        (COLOR_TYPE_RGB, 3)
+    } else {
+        (COLOR_TYPE_RGBA, 4)
+    };
+    let row_len = width as usize * channels;
+
+    // every row starts with the filter that was used on it
+    let mut filtered = Vec::with_capacity((row_len + 1) * height as usize);
+    let mut previous = vec![0u8; row_len];
+    let mut current = Vec::with_capacity(row_len);
+    let mut candidate = vec![0u8; row_len];
+    let mut best = vec![0u8; row_len];
+    for row in rows.take(height as usize) {
+        current.clear();
+        for &pixel in &row[..width as usize] {
+            let rgba = [
+                red_part(pixel) as u8,
+                green_part(pixel) as u8,
+                blue_part(pixel) as u8,
+                alpha_part(pixel) as u8,
+            ];
+            current.extend_from_slice(&rgba[..channels]);
+        }
+        // the filter with the smallest sum of absolute differences usually compresses best
+        let mut best_filter = 0;
+        let mut best_score = u64::MAX;
+        for filter in 0..5 {
+            filter_row(filter, channels, &current, &previous, &mut candidate);
+            let score = candidate
+                .iter()
+                .map(|&b| (b as i8).unsigned_abs() as u64)
+                .sum();
+            if score < best_score {
+                best_score = score;
+                best_filter = filter;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        filtered.push(best_filter);
+        filtered.extend_from_slice(&best);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    writer.write_all(&SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth, color type, compression, filter and interlace method
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+    write_chunk(&mut writer, b"IHDR", &header)?;
+    write_chunk(&mut writer, b"IDAT", &zlib::compress(&filtered))?;
+    write_chunk(&mut writer, b"IEND", &[])?;
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(ImageError::InvalidSignature);
+    }
+    let mut data = &data[SIGNATURE.len()..];
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    loop {
+        if data.len() < 12 {
+            return Err(ImageError::UnexpectedEof);
+        }
+        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        if data.len() - 12 < len {
+            return Err(ImageError::UnexpectedEof);
+        }
+        let kind: [u8; 4] = [data[4], data[5], data[6], data[7]];
+        let chunk = &data[8..8 + len];
+        let crc = &data[8 + len..12 + len];
+        if !crc32_update(crc32_update(!0, &kind), chunk)
+            != u32::from_be_bytes(crc.try_into().unwrap())
+        {
+            let kind = String::from_utf8_lossy(&kind);
+            return Err(ImageError::InvalidData(format!(
+                "crc mismatch in {kind} chunk"
+            )));
+        }
+        data = &data[12 + len..];
+        if header.is_none() && &kind != b"IHDR" {
+            return Err(ImageError::InvalidHeader(
+                "IHDR is not the first chunk".to_string(),
+            ));
+        }
+        match &kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // ancillary chunks, with a lowercase first letter, can be skipped
+            _ if kind[0].is_ascii_lowercase() => {}
+            _ => {
+                let kind = String::from_utf8_lossy(&kind);
+                return Err(ImageError::Unsupported(format!("critical chunk {kind}")));
+            }
+        }
+    }
+    let header = header.ok_or(ImageError::UnexpectedEof)?;
+    if header.color_type == COLOR_TYPE_PALETTE && palette.is_empty() {
+        return Err(ImageError::InvalidData("missing PLTE chunk".to_string()));
+    }
+
+    let bits_per_pixel = header.channels() * header.bit_depth as usize;
+    // filters work on whole bytes, pixels smaller than a byte use the previous byte
+    let bpp = bits_per_pixel.div_ceil(8);
+    let too_large = || ImageError::InvalidHeader("image too large".to_string());
+    // every row starts with its filter byte, the sizes can overflow on 32 bit targets
+    let (row_len, raw_len) = (header.width as usize)
+        .checked_mul(bits_per_pixel)
+        .map(|bits| bits.div_ceil(8))
+        .and_then(|row_len| Some((row_len, (row_len + 1).checked_mul(header.height as usize)?)))
+        .ok_or_else(too_large)?;
+    // the pixels take up to 32 times the filtered data, which itself compresses very well,
+    // so their memory is reserved up front and a failure is an error instead of an abort
+    let len = (header.width as usize)
+        .checked_mul(header.height as usize)
+        .ok_or_else(too_large)?;
+    let mut pixels: Vec<u32> = Vec::new();
+    pixels.try_reserve_exact(len).map_err(|_| too_large())?;
+
+    let mut raw = zlib::decompress(&compressed, raw_len)?;
+    if raw.len() < raw_len {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let mut previous = vec![0u8; row_len];
+    let mut row_pixels = vec![0u32; header.width as usize];
+    for row in raw.chunks_exact_mut(row_len + 1) {
+        let (filter, row) = row.split_first_mut().unwrap();
+        unfilter_row(*filter, bpp, row, &previous)?;
+        header.convert_row(row, &mut row_pixels, palette, transparency);
+        pixels.extend_from_slice(&row_pixels);
+        previous.copy_from_slice(row);
+    }
+    Ok(Image::from_pixels(header.width, header.height, pixels))
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+impl Header {
+    fn parse(chunk: &[u8]) -> Result<Self, ImageError> {
+        if chunk.len() != 13 {
+            return Err(ImageError::InvalidHeader("IHDR length".to_string()));
+        }
+        let width = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        let height = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let [bit_depth, color_type, compression, filter, interlace] =
+            [chunk[8], chunk[9], chunk[10], chunk[11], chunk[12]];
+        if width == 0 || height == 0 {
+            return Err(ImageError::InvalidHeader(format!(
+                "empty image {width}x{height}"
+            )));
+        }
+        // the spec limits both to 2^31 - 1
+        if width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(ImageError::InvalidHeader(format!(
+                "image size {width}x{height}"
+            )));
+        }
+        let valid_depths: &[u8] = match color_type {
+            COLOR_TYPE_GRAY => &[1, 2, 4, 8, 16],
+            COLOR_TYPE_PALETTE => &[1, 2, 4, 8],
+            COLOR_TYPE_RGB | COLOR_TYPE_GRAY_ALPHA | COLOR_TYPE_RGBA => &[8, 16],
+            _ => {
+                return Err(ImageError::InvalidHeader(format!(
+                    "color type {color_type}"
+                )))
+            }
+        };
+        if !valid_depths.contains(&bit_depth) {
+            return Err(ImageError::InvalidHeader(format!(
+                "bit depth {bit_depth} with color type {color_type}"
+            )));
+        }
+        if compression != 0 || filter != 0 {
+            return Err(ImageError::InvalidHeader(
+                "unknown compression or filter method".to_string(),
+            ));
+        }
+        match interlace {
+            0 => {}
+            1 => return Err(ImageError::Unsupported("interlaced image".to_string())),
+            _ => {
+                return Err(ImageError::InvalidHeader(format!(
+                    "interlace method {interlace}"
+                )))
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+        })
+    }
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_TYPE_GRAY | COLOR_TYPE_PALETTE => 1,
+            COLOR_TYPE_GRAY_ALPHA => 2,
+            COLOR_TYPE_RGB => 3,
+            _ => 4,
+        }
+    }
+    fn convert_row(&self, row: &[u8], pixels: &mut [u32], palette: &[u8], transparency: &[u8]) {
+        let depth = self.bit_depth as usize;
+        let channels = self.channels();
+        let max = (1u32 << depth) - 1;
+        // sample c of pixel x, at its full depth
+        let sample = |x: usize, c: usize| -> u32 {
+            let i = x * channels + c;
+            match depth {
+                8 => row[i] as u32,
+                16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]) as u32,
+                _ => {
+                    let bit = i * depth;
+                    (row[bit / 8] as u32 >> (8 - depth - bit % 8)) & max
+                }
+            }
+        };
+        let to_u8 = |value: u32| (value * 255 + max / 2) / max;
+        // a single color that is fully transparent
+        let transparent = |values: &[u32]| {
+            transparency.len() == 2 * values.len()
+                && values.iter().enumerate().all(|(c, &v)| {
+                    u16::from_be_bytes([transparency[2 * c], transparency[2 * c + 1]]) as u32 == v
+                })
+        };
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = match self.color_type {
+                COLOR_TYPE_GRAY => {
+                    let v = sample(x, 0);
+                    let a = if transparent(&[v]) { 0 } else { 0xff };
+                    let v = to_u8(v);
+                    rgba2color(v, v, v, a)
+                }
+                COLOR_TYPE_RGB => {
+                    let rgb = [sample(x, 0), sample(x, 1), sample(x, 2)];
+                    let a = if transparent(&rgb) { 0 } else { 0xff };
+                    rgba2color(to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]), a)
+                }
+                COLOR_TYPE_PALETTE => {
+                    let i = sample(x, 0) as usize;
+                    // out of range indices are an error, reading them as black keeps the image
+                    match palette.get(3 * i..3 * i + 3) {
+                        Some(rgb) => {
+                            let a = transparency.get(i).copied().unwrap_or(0xff);
+                            rgba2color(rgb[0] as u32, rgb[1] as u32, rgb[2] as u32, a as u32)
+                        }
+                        None => 0xff_000000,
+                    }
+                }
+                COLOR_TYPE_GRAY_ALPHA => {
+                    let v = to_u8(sample(x, 0));
+                    rgba2color(v, v, v, to_u8(sample(x, 1)))
+                }
+                _ => rgba2color(
+                    to_u8(sample(x, 0)),
+                    to_u8(sample(x, 1)),
+                    to_u8(sample(x, 2)),
+                    to_u8(sample(x, 3)),
+                ),
+            };
+        }
+    }
+}
+
+fn unfilter_row(filter: u8, bpp: usize, row: &mut [u8], previous: &[u8]) -> Result<(), ImageError> {
+    if filter > 4 {
+        return Err(ImageError::InvalidData(format!("filter type {filter}")));
+    }
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = previous[i];
+        let c = if i >= bpp { previous[i - bpp] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        row[i] = row[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn filter_row(filter: u8, bpp: usize, row: &[u8], previous: &[u8], out: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = previous[i];
+        let c = if i >= bpp { previous[i - bpp] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        out[i] = row[i].wrapping_sub(predictor);
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32_update(crc32_update(!0, kind), data);
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{test_image, test_image_opaque};
+
+    fn png(ihdr: &[u8], chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        write_chunk(&mut data, b"IHDR", ihdr).unwrap();
+        for (kind, chunk) in chunks {
+            write_chunk(&mut data, kind, chunk).unwrap();
+        }
+        write_chunk(&mut data, b"IEND", &[]).unwrap();
+        data
+    }
+
+    fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+        ihdr
+    }
+
+    #[test]
+    fn round_trip() {
+        for image in [test_image(), test_image_opaque()] {
+            let mut data = Vec::new();
+            write(&mut data, image.width, image.height, image.rows()).unwrap();
+            assert_eq!(decode(&data).unwrap(), image);
+        }
+    }
+
+    #[test]
+    fn palette_with_transparency() {
+        // 2 bits per pixel, 5 pixels so the last byte of a row is partly padding,
+        // the second row uses the up filter, so its indices are 0, 1, 2, 2, 0
+        let raw = [0, 0b00_01_10_11, 0b01_000000, 2, 0b11_11_11_11, 0b11_000000];
+        let data = png(
+            &ihdr(5, 2, 2, COLOR_TYPE_PALETTE),
+            &[
+                (
+                    b"PLTE",
+                    &[0xff, 0, 0, 0, 0xff, 0, 0, 0, 0xff, 0xff, 0xff, 0xff],
+                ),
+                (b"tRNS", &[0x00, 0x80]),
+                (b"IDAT", &zlib::compress(&raw)),
+            ],
+        );
+        let image = decode(&data).unwrap();
+        assert_eq!(
+            image.pixels,
+            [
+                0x00_0000ff,
+                0x80_00ff00,
+                0xff_ff0000,
+                0xff_ffffff,
+                0x80_00ff00,
+                0x00_0000ff,
+                0x80_00ff00,
+                0xff_ff0000,
+                0xff_ff0000,
+                0x00_0000ff,
+            ]
+        );
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let image = test_image();
+        let mut data = Vec::new();
+        write(&mut data, image.width, image.height, image.rows()).unwrap();
+        // the last byte of the IHDR chunk, its interlace method
+        data[SIGNATURE.len() + 8 + 12] ^= 1;
+        assert!(matches!(decode(&data), Err(ImageError::InvalidData(_))));
+    }
+
+    #[test]
+    fn oversize_header() {
+        let compressed = zlib::compress(&[0]);
+        for (width, height) in [(1 << 31, 1), (1, u32::MAX)] {
+            let data = png(
+                &ihdr(width, height, 8, COLOR_TYPE_RGBA),
+                &[(b"IDAT", &compressed)],
+            );
+            assert!(matches!(decode(&data), Err(ImageError::InvalidHeader(_))));
+        }
+    }
+}
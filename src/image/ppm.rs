@@ -0,0 +1,343 @@
+// Netpbm decoder: PBM (P1, P4), PGM (P2, P5), PPM (P3, P6) and PAM (P7),
+// and P6 encoder
+use std::io::{self, Read, Write};
+
+use super::{Image, ImageError};
+use crate::renderer::{blue_part, green_part, red_part, rgba2color};
+
+// rows are width pixels long, in the 0xAABBGGRR layout, alpha is dropped
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+) -> io::Result<()> {
+    write!(writer, "P6\n{} {} 255\n", width, height)?;
+    let mut bytes = Vec::with_capacity(width as usize * 3);
+    for row in rows.take(height as usize) {
+        bytes.clear();
+        for &pixel in &row[..width as usize] {
+            bytes.extend_from_slice(&[
+                red_part(pixel) as u8,
+                green_part(pixel) as u8,
+                blue_part(pixel) as u8,
+            ]);
+        }
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    let mut parser = Parser { data, pos: 0 };
+    if data.len() < 2 || data[0] != b'P' {
+        return Err(ImageError::InvalidSignature);
+    }
+    let kind = data[1];
+    parser.pos = 2;
+    if kind == b'7' {
+        return decode_pam(&mut parser);
+    }
+    let channels = match kind {
+        b'1' | b'4' | b'2' | b'5' => 1,
+        b'3' | b'6' => 3,
+        _ => return Err(ImageError::InvalidSignature),
+    };
+    let width = parser.header_number("width")?;
+    let height = parser.header_number("height")?;
+    let maxval = if kind == b'1' || kind == b'4' {
+        1
+    } else {
+        parser.header_number("maxval")?
+    };
+    check_header(width, height, maxval)?;
+    match kind {
+        b'1' | b'2' | b'3' => parser.check_len(width, height, channels)?,
+        b'4' => {
+            parser.single_whitespace()?;
+            parser.check_len((width as usize).div_ceil(8) as u32, height, 1)?;
+        }
+        _ => {
+            parser.single_whitespace()?;
+            parser.check_len(width, height, channels * sample_len(maxval))?;
+        }
+    }
+    let mut image = Image::new(width, height);
+    match kind {
+        // pbm: 1 is black
+        b'1' => {
+            for pixel in image.pixels.iter_mut() {
+                *pixel = if parser.ascii_bit()? {
+                    0xff_000000
+                } else {
+                    0xff_ffffff
+                };
+            }
+        }
+        b'4' => {
+            let row_len = (width as usize).div_ceil(8);
+            let raster = parser.take(row_len * height as usize)?;
+            for (row, pixels) in raster
+                .chunks_exact(row_len)
+                .zip(image.pixels.chunks_exact_mut(width as usize))
+            {
+                for (x, pixel) in pixels.iter_mut().enumerate() {
+                    let bit = row[x / 8] & (0x80 >> (x % 8)) != 0;
+                    *pixel = if bit { 0xff_000000 } else { 0xff_ffffff };
+                }
+            }
+        }
+        b'2' | b'3' => {
+            let mut samples = [0u32; 3];
+            for pixel in image.pixels.iter_mut() {
+                for sample in samples[..channels].iter_mut() {
+                    *sample = scale(parser.ascii_sample(maxval)?, maxval);
+                }
+                *pixel = samples_to_color(&samples[..channels]);
+            }
+        }
+        _ => {
+            decode_binary(&mut parser, &mut image.pixels, channels, maxval)?;
+        }
+    }
+    Ok(image)
+}
+
+fn decode_pam(parser: &mut Parser) -> Result<Image, ImageError> {
+    let (mut width, mut height, mut depth, mut maxval) = (None, None, None, None);
+    loop {
+        parser.skip_whitespace_and_comments();
+        let key = parser.word();
+        match key {
+            b"" => return Err(ImageError::UnexpectedEof),
+            b"ENDHDR" => break,
+            b"WIDTH" => width = Some(parser.header_number("width")?),
+            b"HEIGHT" => height = Some(parser.header_number("height")?),
+            b"DEPTH" => depth = Some(parser.header_number("depth")?),
+            b"MAXVAL" => maxval = Some(parser.header_number("maxval")?),
+            // the depth alone tells how to read the tuples
+            b"TUPLTYPE" => parser.skip_line(),
+            _ => {
+                let key = String::from_utf8_lossy(key);
+                return Err(ImageError::InvalidHeader(format!("unknown field {key}")));
+            }
+        }
+    }
+    parser.skip_line();
+    let missing = |field: &str| ImageError::InvalidHeader(format!("missing {field}"));
+    let width = width.ok_or_else(|| missing("WIDTH"))?;
+    let height = height.ok_or_else(|| missing("HEIGHT"))?;
+    let depth = depth.ok_or_else(|| missing("DEPTH"))?;
+    let maxval = maxval.ok_or_else(|| missing("MAXVAL"))?;
+    check_header(width, height, maxval)?;
+    if !(1..=4).contains(&depth) {
+        return Err(ImageError::Unsupported(format!("depth {depth}")));
+    }
+    parser.check_len(width, height, depth as usize * sample_len(maxval))?;
+    let mut image = Image::new(width, height);
+    decode_binary(parser, &mut image.pixels, depth as usize, maxval)?;
+    Ok(image)
+}
+
+fn check_header(width: u32, height: u32, maxval: u32) -> Result<(), ImageError> {
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidHeader(format!(
+            "empty image {width}x{height}"
+        )));
+    }
+    if !(1..=65535).contains(&maxval) {
+        return Err(ImageError::InvalidHeader(format!("maxval {maxval}")));
+    }
+    Ok(())
+}
+
+fn decode_binary(
+    parser: &mut Parser,
+    pixels: &mut [u32],
+    channels: usize,
+    maxval: u32,
+) -> Result<(), ImageError> {
+    let sample_len = sample_len(maxval);
+    let raster = parser.take(pixels.len() * channels * sample_len)?;
+    let mut samples = [0u32; 4];
+    for (pixel, tuple) in pixels
+        .iter_mut()
+        .zip(raster.chunks_exact(channels * sample_len))
+    {
+        for (sample, bytes) in samples.iter_mut().zip(tuple.chunks_exact(sample_len)) {
+            let value = bytes.iter().fold(0, |v, &b| (v << 8) | b as u32);
+            if value > maxval {
+                return Err(ImageError::InvalidData(format!(
+                    "sample {value} is greater than maxval {maxval}"
+                )));
+            }
+            *sample = scale(value, maxval);
+        }
+        *pixel = samples_to_color(&samples[..channels]);
+    }
+    Ok(())
+}
+
+// samples take two bytes, big endian, when maxval doesn't fit in one
+fn sample_len(maxval: u32) -> usize {
+    if maxval < 256 {
+        1
+    } else {
+        2
+    }
+}
+
+// rescales a sample from 0..=maxval to 0..=255
+fn scale(value: u32, maxval: u32) -> u32 {
+    (value * 255 + maxval / 2) / maxval
+}
+
+fn samples_to_color(samples: &[u32]) -> u32 {
+    match *samples {
+        [v] => rgba2color(v, v, v, 0xff),
+        [v, a] => rgba2color(v, v, v, a),
+        [r, g, b] => rgba2color(r, g, b, 0xff),
+        [r, g, b, a] => rgba2color(r, g, b, a),
+        _ => unreachable!(),
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == b'\n' {
+                break;
+            }
+        }
+    }
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == b'#' {
+                self.skip_line();
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    fn word(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+    fn number(&mut self) -> Result<Option<u32>, ImageError> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        let mut value = 0u32;
+        while let Some(c @ b'0'..=b'9') = self.peek() {
+            value = match value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((c - b'0') as u32))
+            {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+            self.pos += 1;
+        }
+        if self.pos == start {
+            if self.peek().is_none() {
+                return Err(ImageError::UnexpectedEof);
+            }
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+    fn header_number(&mut self, what: &str) -> Result<u32, ImageError> {
+        self.number()?
+            .ok_or_else(|| ImageError::InvalidHeader(format!("invalid {what}")))
+    }
+    fn ascii_sample(&mut self, maxval: u32) -> Result<u32, ImageError> {
+        match self.number()? {
+            Some(value) if value <= maxval => Ok(value),
+            Some(value) => Err(ImageError::InvalidData(format!(
+                "sample {value} is greater than maxval {maxval}"
+            ))),
+            None => Err(ImageError::InvalidData("invalid sample".to_string())),
+        }
+    }
+    // plain pbm bits don't need to be separated by whitespace
+    fn ascii_bit(&mut self) -> Result<bool, ImageError> {
+        self.skip_whitespace_and_comments();
+        let bit = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            Some(_) => return Err(ImageError::InvalidData("invalid bit".to_string())),
+            None => return Err(ImageError::UnexpectedEof),
+        };
+        self.pos += 1;
+        Ok(bit)
+    }
+    // checked before allocating the image, so a header can't claim more pixels than the data has,
+    // every ascii sample takes at least one byte too
+    fn check_len(&self, width: u32, height: u32, pixel_len: usize) -> Result<(), ImageError> {
+        let len = (width as u64 * height as u64).checked_mul(pixel_len as u64);
+        match len {
+            Some(len) if len <= (self.data.len() - self.pos) as u64 => Ok(()),
+            _ => Err(ImageError::UnexpectedEof),
+        }
+    }
+    // a single whitespace separates the header from the binary raster
+    fn single_whitespace(&mut self) -> Result<(), ImageError> {
+        match self.peek() {
+            Some(c) if c.is_ascii_whitespace() => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(ImageError::InvalidHeader(
+                "expected whitespace before the raster".to_string(),
+            )),
+            None => Err(ImageError::UnexpectedEof),
+        }
+    }
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ImageError> {
+        if self.data.len() - self.pos < len {
+            return Err(ImageError::UnexpectedEof);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::test_image_opaque;
+
+    #[test]
+    fn round_trip() {
+        let image = test_image_opaque();
+        let mut data = Vec::new();
+        write(&mut data, image.width, image.height, image.rows()).unwrap();
+        assert_eq!(decode(&data).unwrap(), image);
+    }
+
+    #[test]
+    fn ascii() {
+        let data = b"P3\n# a comment\n3 1\n15\n15 0 0  0 15 0\n0 0 15\n";
+        let image = decode(data).unwrap();
+        assert_eq!(image.pixels, [0xff_0000ff, 0xff_00ff00, 0xff_ff0000]);
+    }
+}
@@ -0,0 +1,247 @@
+// QOI, the "Quite OK Image" format, see https://qoiformat.org/qoi-specification.pdf
+use std::io::{self, Read, Write};
+
+use super::{Image, ImageError};
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+const MAGIC: &[u8; 4] = b"qoif";
+const HEADER_LEN: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xc0;
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const OP_MASK: u8 = 0xc0;
+
+fn hash(pixel: [u8; 4]) -> usize {
+    let [r, g, b, a] = pixel.map(|c| c as usize);
+    (r * 3 + g * 5 + b * 7 + a * 11) % 64
+}
+
+fn rgba(color: u32) -> [u8; 4] {
+    [
+        red_part(color) as u8,
+        green_part(color) as u8,
+        blue_part(color) as u8,
+        alpha_part(color) as u8,
+    ]
+}
+
+// rows are width pixels long, in the 0xAABBGGRR layout,
+// written with 3 channels when every pixel is opaque and 4 otherwise
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+) -> io::Result<()> {
+    let opaque = rows
+        .clone()
+        .all(|row| row.iter().all(|&pixel| alpha_part(pixel) == 0xff));
+    let mut header = [0u8; HEADER_LEN];
+    header[..4].copy_from_slice(MAGIC);
+    header[4..8].copy_from_slice(&width.to_be_bytes());
+    header[8..12].copy_from_slice(&height.to_be_bytes());
+    header[12] = if opaque { 3 } else { 4 };
+    // sRGB with linear alpha
+    header[13] = 0;
+    writer.write_all(&header)?;
+
+    let mut index = [[0u8; 4]; 64];
+    let mut previous = [0, 0, 0, 0xff];
+    let mut run = 0u8;
+    let mut bytes = Vec::new();
+    for row in rows.take(height as usize) {
+        bytes.clear();
+        for &color in &row[..width as usize] {
+            let pixel = rgba(color);
+            if pixel == previous {
+                run += 1;
+                if run == 62 {
+                    bytes.push(OP_RUN | (run - 1));
+                    run = 0;
+                }
+                continue;
+            }
+            if run > 0 {
+                bytes.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            let i = hash(pixel);
+            if index[i] == pixel {
+                bytes.push(OP_INDEX | i as u8);
+            } else {
+                index[i] = pixel;
+                if pixel[3] == previous[3] {
+                    let dr = pixel[0].wrapping_sub(previous[0]) as i8;
+                    let dg = pixel[1].wrapping_sub(previous[1]) as i8;
+                    let db = pixel[2].wrapping_sub(previous[2]) as i8;
+                    let dr_dg = dr.wrapping_sub(dg);
+                    let db_dg = db.wrapping_sub(dg);
+                    if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                        bytes.push(
+                            OP_DIFF
+                                | ((dr + 2) as u8) << 4
+                                | ((dg + 2) as u8) << 2
+                                | (db + 2) as u8,
+                        );
+                    } else if (-32..=31).contains(&dg)
+                        && (-8..=7).contains(&dr_dg)
+                        && (-8..=7).contains(&db_dg)
+                    {
+                        bytes.push(OP_LUMA | (dg + 32) as u8);
+                        bytes.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                    } else {
+                        bytes.extend_from_slice(&[OP_RGB, pixel[0], pixel[1], pixel[2]]);
+                    }
+                } else {
+                    bytes.push(OP_RGBA);
+                    bytes.extend_from_slice(&pixel);
+                }
+            }
+            previous = pixel;
+        }
+        writer.write_all(&bytes)?;
+    }
+    if run > 0 {
+        writer.write_all(&[OP_RUN | (run - 1)])?;
+    }
+    writer.write_all(&END_MARKER)?;
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ImageError::InvalidSignature);
+    }
+    if data.len() < HEADER_LEN {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let width = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let height = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    let (channels, colorspace) = (data[12], data[13]);
+    // empty images are accepted, so what write produces for an empty canvas can be read back
+    if channels != 3 && channels != 4 {
+        return Err(ImageError::InvalidHeader(format!("{channels} channels")));
+    }
+    if colorspace > 1 {
+        return Err(ImageError::InvalidHeader(format!(
+            "colorspace {colorspace}"
+        )));
+    }
+    let len = width as u64 * height as u64;
+    // a run encodes at most 62 pixels in one byte
+    if (data.len() - HEADER_LEN) as u64 * 62 < len {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let len = len as usize;
+    let mut pixels = Vec::with_capacity(len);
+    let mut index = [[0u8; 4]; 64];
+    let mut pixel = [0, 0, 0, 0xff];
+    let mut pos = HEADER_LEN;
+    let mut next = || -> Result<u8, ImageError> {
+        let byte = *data.get(pos).ok_or(ImageError::UnexpectedEof)?;
+        pos += 1;
+        Ok(byte)
+    };
+    while pixels.len() < len {
+        let op = next()?;
+        match op {
+            OP_RGB => {
+                pixel = [next()?, next()?, next()?, pixel[3]];
+            }
+            OP_RGBA => {
+                pixel = [next()?, next()?, next()?, next()?];
+            }
+            _ => match op & OP_MASK {
+                OP_INDEX => pixel = index[op as usize],
+                OP_DIFF => {
+                    pixel[0] = pixel[0].wrapping_add((op >> 4) & 0x03).wrapping_sub(2);
+                    pixel[1] = pixel[1].wrapping_add((op >> 2) & 0x03).wrapping_sub(2);
+                    pixel[2] = pixel[2].wrapping_add(op & 0x03).wrapping_sub(2);
+                }
+                OP_LUMA => {
+                    let byte = next()?;
+                    let dg = (op & 0x3f).wrapping_sub(32);
+                    pixel[0] = pixel[0]
+                        .wrapping_add(dg)
+                        .wrapping_add(byte >> 4)
+                        .wrapping_sub(8);
+                    pixel[1] = pixel[1].wrapping_add(dg);
+                    pixel[2] = pixel[2]
+                        .wrapping_add(dg)
+                        .wrapping_add(byte & 0x0f)
+                        .wrapping_sub(8);
+                }
+                _ => {
+                    // like every other op, a run puts its pixel in the index,
+                    // which matters when the stream starts with a run of the initial pixel
+                    index[hash(pixel)] = pixel;
+                    let run = (op & 0x3f) as usize + 1;
+                    let color = rgba2color(
+                        pixel[0] as u32,
+                        pixel[1] as u32,
+                        pixel[2] as u32,
+                        pixel[3] as u32,
+                    );
+                    let run = run.min(len - pixels.len());
+                    pixels.extend(std::iter::repeat_n(color, run));
+                    continue;
+                }
+            },
+        }
+        index[hash(pixel)] = pixel;
+        let [r, g, b, a] = pixel.map(|c| c as u32);
+        pixels.push(rgba2color(r, g, b, a));
+    }
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{test_image, test_image_opaque};
+
+    #[test]
+    fn round_trip() {
+        // runs longer than 62 pixels, and the index, diff and luma ops
+        for image in [test_image(), test_image_opaque()] {
+            let mut data = Vec::new();
+            write(&mut data, image.width, image.height, image.rows()).unwrap();
+            assert_eq!(decode(&data).unwrap(), image);
+        }
+    }
+
+    #[test]
+    fn run_updates_index() {
+        // the initial pixel, opaque black, once as a run and once from its index
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&[4, 0]);
+        assert_eq!(hash([0, 0, 0, 0xff]), 53);
+        data.extend_from_slice(&[OP_RUN, OP_INDEX | 53]);
+        data.extend_from_slice(&END_MARKER);
+        let image = decode(&data).unwrap();
+        assert_eq!(image.pixels, [0xff_000000, 0xff_000000]);
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        for (width, height) in [(0, 0), (0, 3), (5, 0)] {
+            let image = Image::new(width, height);
+            let mut data = Vec::new();
+            write(&mut data, width, height, image.rows()).unwrap();
+            assert_eq!(decode(&data).unwrap(), image);
+        }
+    }
+}
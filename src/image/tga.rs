@@ -0,0 +1,247 @@
+// TGA reader for 8 bit grayscale, 24 and 32 bit true color images, raw or run length encoded,
+// and writer, 24 bit when every pixel is opaque, 32 bit otherwise
+use std::io::{self, Read, Write};
+
+use super::{Image, ImageError};
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+const HEADER_LEN: usize = 18;
+
+const TYPE_TRUE_COLOR: u8 = 2;
+const TYPE_GRAY: u8 = 3;
+const TYPE_RLE_TRUE_COLOR: u8 = 10;
+const TYPE_RLE_GRAY: u8 = 11;
+
+const DESCRIPTOR_RIGHT_TO_LEFT: u8 = 0x10;
+const DESCRIPTOR_TOP_DOWN: u8 = 0x20;
+
+// rows are width pixels long, in the 0xAABBGGRR layout
+pub fn write<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+    rle: bool,
+) -> io::Result<()> {
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "TGA images are at most 65535x65535",
+        ));
+    }
+    let opaque = rows
+        .clone()
+        .all(|row| row.iter().all(|&pixel| alpha_part(pixel) == 0xff));
+    let (bits, alpha_bits) = if opaque { (24u8, 0u8) } else { (32, 8) };
+    let image_type = if rle {
+        TYPE_RLE_TRUE_COLOR
+    } else {
+        TYPE_TRUE_COLOR
+    };
+    let mut header = [0u8; HEADER_LEN];
+    header[2] = image_type;
+    header[12..14].copy_from_slice(&(width as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(height as u16).to_le_bytes());
+    header[16] = bits;
+    header[17] = alpha_bits | DESCRIPTOR_TOP_DOWN;
+    writer.write_all(&header)?;
+
+    let bytes_per_pixel = bits as usize / 8;
+    let mut bytes = Vec::new();
+    for row in rows.take(height as usize) {
+        let row = &row[..width as usize];
+        bytes.clear();
+        let push_pixel = |bytes: &mut Vec<u8>, pixel: u32| {
+            let bgra = [
+                blue_part(pixel) as u8,
+                green_part(pixel) as u8,
+                red_part(pixel) as u8,
+                alpha_part(pixel) as u8,
+            ];
+            bytes.extend_from_slice(&bgra[..bytes_per_pixel]);
+        };
+        if !rle {
+            row.iter().for_each(|&pixel| push_pixel(&mut bytes, pixel));
+            writer.write_all(&bytes)?;
+            continue;
+        }
+        // packets hold at most 128 pixels and don't cross rows
+        let mut x = 0;
+        while x < row.len() {
+            let run = row[x..]
+                .iter()
+                .take(128)
+                .take_while(|&&pixel| pixel == row[x])
+                .count();
+            if run > 1 {
+                bytes.push(0x80 | (run - 1) as u8);
+                push_pixel(&mut bytes, row[x]);
+                x += run;
+                continue;
+            }
+            // raw packet up to the next run of at least 2 pixels
+            let mut end = x + 1;
+            while end < row.len() && end - x < 128 && row[end] != row[end - 1] {
+                end += 1;
+            }
+            if end < row.len() && end - x < 128 && row[end] == row[end - 1] {
+                end -= 1;
+            }
+            bytes.push((end - x - 1) as u8);
+            row[x..end]
+                .iter()
+                .for_each(|&pixel| push_pixel(&mut bytes, pixel));
+            x = end;
+        }
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()
+}
+
+pub fn read(mut reader: impl Read) -> Result<Image, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    // TGA has no signature, the header is checked for values that make sense instead
+    if data.len() < HEADER_LEN {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let id_len = data[0] as usize;
+    let color_map_type = data[1];
+    let image_type = data[2];
+    let color_map_len = u16::from_le_bytes([data[5], data[6]]) as usize;
+    let color_map_entry_bits = data[7] as usize;
+    let width = u16::from_le_bytes([data[12], data[13]]) as u32;
+    let height = u16::from_le_bytes([data[14], data[15]]) as u32;
+    let bits = data[16];
+    let descriptor = data[17];
+    if color_map_type > 1 {
+        return Err(ImageError::InvalidSignature);
+    }
+    let gray = match image_type {
+        TYPE_TRUE_COLOR | TYPE_RLE_TRUE_COLOR => false,
+        TYPE_GRAY | TYPE_RLE_GRAY => true,
+        1 | 9 => return Err(ImageError::Unsupported("color mapped image".to_string())),
+        _ => return Err(ImageError::InvalidSignature),
+    };
+    let bytes_per_pixel = match (gray, bits) {
+        (true, 8) => 1,
+        (false, 24) => 3,
+        (false, 32) => 4,
+        _ => return Err(ImageError::Unsupported(format!("{bits} bits per pixel"))),
+    };
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidHeader(format!(
+            "empty image {width}x{height}"
+        )));
+    }
+    let alpha = bytes_per_pixel == 4 && descriptor & 0x0f != 0;
+
+    // a color map can be there even if the image doesn't use it
+    let color_map_bytes = if color_map_type == 1 {
+        color_map_len * color_map_entry_bits.div_ceil(8)
+    } else {
+        0
+    };
+    let mut pos = HEADER_LEN + id_len + color_map_bytes;
+    let len = width as usize * height as usize;
+    let to_color = |bytes: &[u8]| match *bytes {
+        [v] => rgba2color(v as u32, v as u32, v as u32, 0xff),
+        [b, g, r] => rgba2color(r as u32, g as u32, b as u32, 0xff),
+        [b, g, r, a] => rgba2color(
+            r as u32,
+            g as u32,
+            b as u32,
+            if alpha { a as u32 } else { 0xff },
+        ),
+        _ => unreachable!(),
+    };
+    let mut pixels = Vec::new();
+    if image_type == TYPE_RLE_TRUE_COLOR || image_type == TYPE_RLE_GRAY {
+        // every packet takes at least 1 + bytes_per_pixel bytes for up to 128 pixels
+        if (data.len().saturating_sub(pos) as u64) * 128 < len as u64 {
+            return Err(ImageError::UnexpectedEof);
+        }
+        pixels.reserve(len);
+        while pixels.len() < len {
+            let packet = *data.get(pos).ok_or(ImageError::UnexpectedEof)?;
+            pos += 1;
+            let count = (packet & 0x7f) as usize + 1;
+            if count > len - pixels.len() {
+                return Err(ImageError::InvalidData(
+                    "run length packet past the end of the image".to_string(),
+                ));
+            }
+            let pixel_bytes = if packet & 0x80 != 0 {
+                bytes_per_pixel
+            } else {
+                bytes_per_pixel * count
+            };
+            let bytes = data
+                .get(pos..pos + pixel_bytes)
+                .ok_or(ImageError::UnexpectedEof)?;
+            pos += pixel_bytes;
+            if packet & 0x80 != 0 {
+                let color = to_color(bytes);
+                pixels.extend(std::iter::repeat_n(color, count));
+            } else {
+                pixels.extend(bytes.chunks_exact(bytes_per_pixel).map(to_color));
+            }
+        }
+    } else {
+        let bytes = data
+            .get(pos..pos + len * bytes_per_pixel)
+            .ok_or(ImageError::UnexpectedEof)?;
+        pixels.extend(bytes.chunks_exact(bytes_per_pixel).map(to_color));
+    }
+
+    // the origin is at the bottom left unless the descriptor says otherwise
+    let mut image = Image::from_pixels(width, height, pixels);
+    if descriptor & DESCRIPTOR_TOP_DOWN == 0 {
+        let w = width as usize;
+        for y in 0..height as usize / 2 {
+            let (top, bottom) = image.pixels.split_at_mut((height as usize - 1 - y) * w);
+            top[y * w..(y + 1) * w].swap_with_slice(&mut bottom[..w]);
+        }
+    }
+    if descriptor & DESCRIPTOR_RIGHT_TO_LEFT != 0 {
+        image
+            .pixels
+            .chunks_exact_mut(width as usize)
+            .for_each(|row| row.reverse());
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{test_image, test_image_opaque, Image};
+
+    fn round_trip(image: &Image, rle: bool) {
+        let mut data = Vec::new();
+        write(&mut data, image.width, image.height, image.rows(), rle).unwrap();
+        assert_eq!(&decode(&data).unwrap(), image, "rle: {rle}");
+    }
+
+    #[test]
+    fn raw_and_rle() {
+        for image in [test_image(), test_image_opaque()] {
+            round_trip(&image, false);
+            round_trip(&image, true);
+        }
+    }
+
+    #[test]
+    fn long_packets() {
+        // a run and a raw stretch both longer than the 128 pixels of a packet
+        let pixels = (0..301)
+            .map(|_| 0xff_336699)
+            .chain((0..301).map(|x| 0x80_000000 | x))
+            .collect();
+        round_trip(&Image::from_pixels(301, 2, pixels), true);
+    }
+}
@@ -0,0 +1,414 @@
+// zlib streams (RFC 1950) with deflate (RFC 1951), compressed with the fixed huffman codes
+use super::ImageError;
+
+const WINDOW_SIZE: usize = 1 << 15;
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN: usize = 64;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+// base and extra bits of the length codes 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// base and extra bits of the distance codes 0..=29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before b overflows
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    // 32K window, no preset dictionary, default compression level
+    let mut writer = BitWriter {
+        bytes: vec![0x78, 0x9c],
+        bits: 0,
+        len: 0,
+    };
+    // a single final block using the fixed codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+    let mut head = vec![u32::MAX; 1 << HASH_BITS];
+    let mut prev = vec![u32::MAX; WINDOW_SIZE];
+    let hash = |i: usize| {
+        let v = (data[i] as u32) | (data[i + 1] as u32) << 8 | (data[i + 2] as u32) << 16;
+        (v.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize
+    };
+    let insert = |i: usize, head: &mut [u32], prev: &mut [u32]| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            prev[i % WINDOW_SIZE] = head[h];
+            head[h] = i as u32;
+        }
+    };
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let max_len = MAX_MATCH.min(data.len() - i);
+            let mut candidate = head[hash(i)];
+            let mut chain = MAX_CHAIN;
+            while candidate != u32::MAX && chain > 0 {
+                let j = candidate as usize;
+                if i - j > WINDOW_SIZE - 1 {
+                    break;
+                }
+                if data[j + best_len] == data[i + best_len] {
+                    let len = data[j..j + max_len]
+                        .iter()
+                        .zip(&data[i..i + max_len])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    if len > best_len {
+                        best_len = len;
+                        best_dist = i - j;
+                        if len == max_len {
+                            break;
+                        }
+                    }
+                }
+                let next = prev[j % WINDOW_SIZE];
+                // the chain only goes backwards, anything else is a stale entry of the ring
+                if next == u32::MAX || next as usize >= j {
+                    break;
+                }
+                candidate = next;
+                chain -= 1;
+            }
+        }
+        if best_len >= MIN_MATCH {
+            writer.write_length(best_len);
+            writer.write_distance(best_dist);
+            for k in i..i + best_len {
+                insert(k, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            writer.write_literal(data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    writer.write_literal(256);
+    let mut bytes = writer.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u64,
+    len: u32,
+}
+impl BitWriter {
+    // least significant bit first
+    fn write(&mut self, value: u32, len: u32) {
+        self.bits |= (value as u64) << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+    // huffman codes are packed starting from their most significant bit
+    fn write_code(&mut self, code: u32, len: u32) {
+        self.write(code.reverse_bits() >> (32 - len), len);
+    }
+    fn write_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+    fn write_length(&mut self, len: usize) {
+        let code = LENGTH_BASE.partition_point(|&base| base as usize <= len) - 1;
+        self.write_literal(257 + code as u16);
+        let extra = LENGTH_EXTRA[code] as u32;
+        self.write((len - LENGTH_BASE[code] as usize) as u32, extra);
+    }
+    fn write_distance(&mut self, dist: usize) {
+        let code = DIST_BASE.partition_point(|&base| base as usize <= dist) - 1;
+        self.write_code(code as u32, 5);
+        let extra = DIST_EXTRA[code] as u32;
+        self.write((dist - DIST_BASE[code] as usize) as u32, extra);
+    }
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+// fails as soon as the output would grow past max_len, so a small stream
+// can't expand into more memory than the caller expects
+pub(crate) fn decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 2 {
+        return Err(ImageError::UnexpectedEof);
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || cmf >> 4 > 7 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(ImageError::InvalidData("invalid zlib header".to_string()));
+    }
+    if flg & 0x20 != 0 {
+        return Err(ImageError::Unsupported(
+            "zlib preset dictionary".to_string(),
+        ));
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        pos: 0,
+        bits: 0,
+        len: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.read(1)? == 1;
+        match reader.read(2)? {
+            0 => {
+                reader.align();
+                let header = reader.take(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(ImageError::InvalidData(
+                        "invalid stored block length".to_string(),
+                    ));
+                }
+                if out.len() + len as usize > max_len {
+                    return Err(too_long());
+                }
+                out.extend_from_slice(reader.take(len as usize)?);
+            }
+            1 => {
+                let mut lengths = [0u8; 288 + 32];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let literals = Huffman::new(&lengths[..288])?;
+                let distances = Huffman::new(&lengths[288..])?;
+                inflate_block(&mut reader, &mut out, max_len, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, max_len, &literals, &distances)?;
+            }
+            _ => return Err(ImageError::InvalidData("invalid block type".to_string())),
+        }
+        if last {
+            break;
+        }
+    }
+    reader.align();
+    let checksum = reader.take(4)?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&out) {
+        return Err(ImageError::InvalidData(
+            "zlib checksum mismatch".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+fn too_long() -> ImageError {
+    ImageError::InvalidData("more data than expected".to_string())
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+    let hlit = reader.read(5)? as usize + 257;
+    let hdist = reader.read(5)? as usize + 1;
+    let hclen = reader.read(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &i in &ORDER[..hclen] {
+        code_lengths[i] = reader.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+    let mut lengths = vec![0u8; hlit + hdist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => match i.checked_sub(1) {
+                Some(previous) => (lengths[previous], 3 + reader.read(2)? as usize),
+                None => {
+                    return Err(ImageError::InvalidData(
+                        "repeat without a previous length".to_string(),
+                    ))
+                }
+            },
+            17 => (0, 3 + reader.read(3)? as usize),
+            _ => (0, 11 + reader.read(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(ImageError::InvalidData("too many code lengths".to_string()));
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(ImageError::InvalidData(
+            "missing end of block code".to_string(),
+        ));
+    }
+    Ok((
+        Huffman::new(&lengths[..hlit])?,
+        Huffman::new(&lengths[hlit..])?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_len: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 if out.len() < max_len => out.push(symbol as u8),
+            0..=255 => return Err(too_long()),
+            256 => return Ok(()),
+            257..=285 => {
+                let code = symbol - 257;
+                let len =
+                    LENGTH_BASE[code] as usize + reader.read(LENGTH_EXTRA[code] as u32)? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DIST_BASE.len() {
+                    return Err(ImageError::InvalidData("invalid distance code".to_string()));
+                }
+                let dist =
+                    DIST_BASE[code] as usize + reader.read(DIST_EXTRA[code] as u32)? as usize;
+                if dist > out.len() {
+                    return Err(ImageError::InvalidData("distance too far back".to_string()));
+                }
+                if out.len() + len > max_len {
+                    return Err(too_long());
+                }
+                // the copy can overlap what it writes
+                let start = out.len() - dist;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => return Err(ImageError::InvalidData("invalid length code".to_string())),
+        }
+    }
+}
+
+// canonical huffman code, decoded one bit at a time
+struct Huffman {
+    // number of codes of every length
+    counts: [u16; 16],
+    // symbols ordered by their code
+    symbols: Vec<u16>,
+}
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        let mut left = 1i32;
+        for len in 1..16 {
+            left = (left << 1) - counts[len] as i32;
+            if left < 0 {
+                return Err(ImageError::InvalidData(
+                    "oversubscribed huffman code".to_string(),
+                ));
+            }
+            if len < 15 {
+                offsets[len + 1] = offsets[len] + counts[len];
+            }
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        // first code of the current length, and index of its symbol
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.read(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ImageError::InvalidData("invalid huffman code".to_string()))
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    len: u32,
+}
+impl<'a> BitReader<'a> {
+    fn read(&mut self, len: u32) -> Result<u32, ImageError> {
+        while self.len < len {
+            let byte = *self.data.get(self.pos).ok_or(ImageError::UnexpectedEof)?;
+            self.pos += 1;
+            self.bits |= (byte as u32) << self.len;
+            self.len += 8;
+        }
+        let value = self.bits & ((1u64 << len) - 1) as u32;
+        self.bits >>= len;
+        self.len -= len;
+        Ok(value)
+    }
+    // drops the bits left in the current byte
+    fn align(&mut self) {
+        self.bits = 0;
+        self.len = 0;
+    }
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ImageError> {
+        if self.data.len() - self.pos < len {
+            return Err(ImageError::UnexpectedEof);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+}
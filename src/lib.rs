@@ -1 +1,13 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod canvas;
+pub mod color;
+#[cfg(feature = "std")]
+pub mod image;
+mod math;
+pub mod pixel_format;
 pub mod renderer;
+#[cfg(feature = "std")]
+pub mod term;
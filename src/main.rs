@@ -1,4 +1,9 @@
-use olive_rs::renderer::Renderer;
+use olive_rs::image::Image;
+use olive_rs::pixel_format::{Gray8, PixelFormat, Rgb565};
+use olive_rs::renderer::{
+    flatten_cubic_bezier, FillRule, Gradient, LineCap, LineJoin, Path, Pattern, Renderer,
+    SpreadMode, FLATTEN_TOLERANCE,
+};
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
@@ -144,6 +149,429 @@ fn line_example() {
     renderer.save_to_ppm_file(file).unwrap();
 }
 
+fn line_aa_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/line_aa.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    let center_x = WIDTH as f32 / 2f32;
+    let center_y = HEIGHT as f32 / 2f32;
+    let r = HEIGHT as f32 / 2f32 - 10f32;
+    let n = 24;
+    for i in 0..n {
+        let angle = i as f32 / n as f32 * 2f32 * std::f32::consts::PI;
+        renderer.draw_line_aa(
+            center_x,
+            center_y,
+            center_x + angle.cos() * r,
+            center_y + angle.sin() * r,
+            FOREGROUND_COLOR,
+        );
+    }
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn thick_line_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/thick_line.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    let caps = [LineCap::Butt, LineCap::Square, LineCap::Round];
+    let joins = [LineJoin::Miter, LineJoin::Bevel, LineJoin::Round];
+    for (i, (&cap, &join)) in caps.iter().zip(joins.iter()).enumerate() {
+        let x = 50f32 + i as f32 * 250f32;
+        let points = [
+            (x, 250f32),
+            (x + 50f32, 100f32),
+            (x + 100f32, 250f32),
+            (x + 200f32, 150f32),
+        ];
+        renderer.draw_polyline_aa(&points, 20f32, cap, join, FOREGROUND_COLOR);
+        renderer.draw_polyline_aa(&points, 1f32, LineCap::Butt, join, 0xff_ffffff);
+    }
+    renderer.begin_blending();
+    renderer.draw_polyline(
+        &[
+            (50f32, 400f32),
+            (400f32, 550f32),
+            (750f32, 400f32),
+            (50f32, 500f32),
+        ],
+        30f32,
+        LineCap::Round,
+        LineJoin::Round,
+        0x88_00ff00,
+    );
+    renderer.end_blending();
+    renderer.draw_thick_line_aa(50.3, 320.6, 750.1, 350.2, 5f32, LineCap::Round, BLUE);
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn outline_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/outline.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    for i in 0..4 {
+        let thickness = 1 + i * 4;
+        let x = 20 + i as i32 * 190;
+        renderer.draw_rect(x, 20, 170, 120, thickness, RED);
+        renderer.draw_circle(x + 85, 240, 80, thickness, GREEN);
+        renderer.draw_circle_aa(x + 85, 420, 80, thickness, GREEN);
+        renderer.draw_triangle_aa(x + 10, 580, x + 85, 520, x + 160, 570, thickness, BLUE);
+    }
+    renderer.begin_blending();
+    renderer.draw_rect(
+        10,
+        10,
+        WIDTH as i32 - 20,
+        HEIGHT as i32 - 20,
+        8,
+        0x88_ffffff,
+    );
+    renderer.draw_triangle(100, 300, 700, 150, 500, 590, 6, 0x88_ffffff);
+    renderer.end_blending();
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn polygon_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/polygon.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    let star = |center_x: f32, center_y: f32, r: f32| -> Vec<(f32, f32)> {
+        (0..5)
+            .map(|i| {
+                let angle = (i * 2) as f32 / 5f32 * 2f32 * std::f32::consts::PI
+                    - std::f32::consts::FRAC_PI_2;
+                (center_x + angle.cos() * r, center_y + angle.sin() * r)
+            })
+            .collect()
+    };
+    renderer.fill_polygon(&star(200f32, 150f32, 140f32), FillRule::EvenOdd, RED);
+    renderer.fill_polygon(&star(600f32, 150f32, 140f32), FillRule::NonZero, RED);
+    renderer.fill_polygon_aa(&star(200f32, 450f32, 140f32), FillRule::EvenOdd, GREEN);
+    renderer.fill_polygon_aa(&star(600f32, 450f32, 140f32), FillRule::NonZero, GREEN);
+    renderer.begin_blending();
+    renderer.fill_polygon_aa(
+        &[
+            (350f32, 250f32),
+            (450f32, 250f32),
+            (450f32, 350f32),
+            (400f32, 300f32),
+            (350f32, 350f32),
+        ],
+        FillRule::NonZero,
+        0x88_ff0000,
+    );
+    renderer.end_blending();
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn ellipse_example() {
+    use std::f32::consts::PI;
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/ellipse.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    renderer.fill_ellipse(150, 150, 120, 60, 0f32, RED);
+    renderer.fill_ellipse_aa(400, 150, 120, 60, PI / 6f32, RED);
+    renderer.draw_arc(650, 150, 100, PI, 2f32 * PI, 20, GREEN);
+    renderer.draw_arc_aa(650, 150, 70, -PI / 4f32, PI / 2f32, 10, GREEN);
+    let colors = [RED, GREEN, BLUE, 0xff_00ffff];
+    let slices = [0.1f32, 0.25, 0.3, 0.35];
+    let mut start = -PI / 2f32;
+    for (&slice, &color) in slices.iter().zip(colors.iter()) {
+        let end = start + slice * 2f32 * PI;
+        renderer.fill_pie(200, 420, 150, start, end, color);
+        renderer.fill_pie_aa(600, 420, 150, start, end, color);
+        start = end;
+    }
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn bezier_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/bezier.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    renderer.draw_quad_bezier(
+        50.0,
+        250.0,
+        200.0,
+        0.0,
+        350.0,
+        250.0,
+        1.0,
+        LineCap::Butt,
+        RED,
+    );
+    renderer.draw_quad_bezier_aa(
+        50.0,
+        280.0,
+        200.0,
+        30.0,
+        350.0,
+        280.0,
+        8.0,
+        LineCap::Round,
+        RED,
+    );
+    renderer.draw_cubic_bezier(
+        50.0,
+        550.0,
+        100.0,
+        300.0,
+        300.0,
+        600.0,
+        350.0,
+        350.0,
+        1.0,
+        LineCap::Butt,
+        GREEN,
+    );
+    renderer.draw_cubic_bezier_aa(
+        50.0,
+        580.0,
+        100.0,
+        330.0,
+        300.0,
+        630.0,
+        350.0,
+        380.0,
+        12.0,
+        LineCap::Square,
+        GREEN,
+    );
+
+    // a heart made of four cubic curves
+    let (x0, y0) = (600.0, 200.0);
+    let mut points = vec![(x0, y0)];
+    flatten_cubic_bezier(
+        &mut points,
+        x0,
+        y0,
+        600.0,
+        100.0,
+        400.0,
+        100.0,
+        450.0,
+        250.0,
+        FLATTEN_TOLERANCE,
+    );
+    flatten_cubic_bezier(
+        &mut points,
+        450.0,
+        250.0,
+        480.0,
+        340.0,
+        600.0,
+        400.0,
+        600.0,
+        450.0,
+        FLATTEN_TOLERANCE,
+    );
+    flatten_cubic_bezier(
+        &mut points,
+        600.0,
+        450.0,
+        600.0,
+        400.0,
+        720.0,
+        340.0,
+        750.0,
+        250.0,
+        FLATTEN_TOLERANCE,
+    );
+    flatten_cubic_bezier(
+        &mut points,
+        750.0,
+        250.0,
+        800.0,
+        100.0,
+        600.0,
+        100.0,
+        x0,
+        y0,
+        FLATTEN_TOLERANCE,
+    );
+    renderer.fill_polygon_aa(&points, FillRule::NonZero, BLUE);
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn path_example() {
+    use std::f32::consts::PI;
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/path.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+
+    // a ring with a hole, made of two arcs
+    let mut ring = Path::new();
+    ring.arc(200.0, 200.0, 150.0, 0.0, 2.0 * PI)
+        .close()
+        .move_to(270.0, 200.0)
+        .arc(200.0, 200.0, 70.0, 0.0, 2.0 * PI)
+        .close();
+    renderer.fill_path_aa(&ring, FillRule::EvenOdd, RED);
+    renderer.stroke_path_aa(&ring, 4.0, LineCap::Butt, LineJoin::Round, 0xff_ffffff);
+
+    let mut shape = Path::new();
+    shape
+        .move_to(450.0, 350.0)
+        .line_to(500.0, 100.0)
+        .quad_to(600.0, 0.0, 700.0, 100.0)
+        .cubic_to(800.0, 200.0, 600.0, 300.0, 750.0, 350.0)
+        .close();
+    renderer.fill_path(&shape, FillRule::NonZero, GREEN);
+    renderer.stroke_path_aa(&shape, 10.0, LineCap::Butt, LineJoin::Miter, BLUE);
+
+    let mut wave = Path::new();
+    wave.move_to(50.0, 500.0);
+    for i in 0..7 {
+        let x = 50.0 + i as f32 * 100.0;
+        wave.quad_to(
+            x + 50.0,
+            if i % 2 == 0 { 400.0 } else { 600.0 },
+            x + 100.0,
+            500.0,
+        );
+    }
+    renderer.stroke_path_aa(&wave, 16.0, LineCap::Round, LineJoin::Round, 0xff_00ffff);
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn rounded_rect_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/rounded_rect.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    renderer.fill_rounded_rect(50, 50, 300, 200, [20, 20, 20, 20], RED);
+    renderer.fill_rounded_rect_aa(450, 50, 300, 200, [0, 40, 80, 120], RED);
+    renderer.draw_rounded_rect(50, 350, 300, 200, [50, 10, 50, 10], 4, GREEN);
+    renderer.draw_rounded_rect_aa(450, 350, 300, 200, [100, 100, 100, 100], 20, GREEN);
+    renderer.begin_blending();
+    renderer.fill_rounded_rect_aa(200, 150, 400, 300, [60, 60, 60, 60], 0x88_ff0000);
+    renderer.end_blending();
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
+fn gradient_example() {
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/gradient.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+
+    let mut linear = Gradient::linear(50.0, 0.0, 350.0, 0.0);
+    linear
+        .add_stop(0.0, RED)
+        .add_stop(0.5, GREEN)
+        .add_stop(1.0, BLUE);
+    renderer.fill_rect_paint(50, 50, 300, 100, &linear);
+    renderer.fill_text_paint("gradient", 50, 160, 4, &linear);
+
+    let mut stripes = Gradient::linear(450.0, 50.0, 490.0, 90.0);
+    stripes
+        .add_stop(0.0, 0xff_ffffff)
+        .add_stop(1.0, 0xff_000000)
+        .set_spread(SpreadMode::Repeat);
+    renderer.fill_rect_paint(450, 50, 140, 100, &stripes);
+    stripes.set_spread(SpreadMode::Reflect);
+    renderer.fill_rect_paint(610, 50, 140, 100, &stripes);
+
+    let mut radial = Gradient::radial(200.0, 350.0, 120.0);
+    radial.add_stop(0.0, 0xff_ffffff).add_stop(1.0, RED);
+    renderer.fill_circle_aa_paint(200, 350, 120, &radial);
+
+    let mut conic = Gradient::conic(600.0, 350.0, 0.0);
+    conic
+        .add_stop(0.0, RED)
+        .add_stop(1.0 / 3.0, GREEN)
+        .add_stop(2.0 / 3.0, BLUE)
+        .add_stop(1.0, RED);
+    renderer.fill_circle_paint(600, 350, 120, &conic);
+
+    let mut warm = Gradient::linear(400.0, 480.0, 400.0, 580.0);
+    warm.add_stop(0.0, 0xff_00ffff).add_stop(1.0, 0xff_0080ff);
+    renderer.fill_triangle_aa_paint(340, 580, 400, 480, 460, 580, &warm);
+    let star = [
+        (100.0, 580.0),
+        (130.0, 490.0),
+        (160.0, 580.0),
+        (85.0, 525.0),
+        (175.0, 525.0),
+    ];
+    renderer.fill_polygon_aa_paint(&star, FillRule::NonZero, &warm);
+
+    renderer.begin_blending();
+    let mut fade = Gradient::linear(0.0, 0.0, WIDTH as f32, 0.0);
+    fade.add_stop(0.0, 0x00_000000).add_stop(1.0, 0xcc_000000);
+    renderer.fill_rect_paint(0, 200, WIDTH as i32, 40, &fade);
+    renderer.end_blending();
+    renderer.save_to_ppm_file(file).unwrap();
+    renderer.save_to_png_file("output/gradient.png").unwrap();
+}
+
+fn paint_example() {
+    let mut tile = [0u32; 32 * 32];
+    let mut tile_renderer = Renderer::new(&mut tile, 32, 32);
+    tile_renderer.fill(0xff_303030);
+    tile_renderer.fill_rect(0, 0, 16, 16, 0xff_c0c0c0);
+    tile_renderer.fill_rect(16, 16, 16, 16, 0xff_c0c0c0);
+    tile_renderer.fill_circle_aa(16, 16, 6, RED);
+
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/paint.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+
+    let pattern = Pattern::from_renderer(&tile_renderer).offset(50, 50);
+    renderer.fill_rounded_rect_aa_paint(50, 50, 300, 200, [30, 30, 30, 30], &pattern);
+    renderer.fill_ellipse_aa_paint(600, 150, 150, 80, 0.3, &pattern);
+
+    let rings = |x: f32, y: f32| {
+        let d = ((x - 200.0).powi(2) + (y - 420.0).powi(2)).sqrt();
+        if ((d / 15.0) as u32).is_multiple_of(2) {
+            GREEN
+        } else {
+            BLUE
+        }
+    };
+    renderer.fill_pie_aa_paint(200, 420, 140, 0.5, 5.8, &rings);
+
+    let mut heart = Path::new();
+    heart
+        .move_to(600.0, 560.0)
+        .cubic_to(480.0, 480.0, 480.0, 360.0, 560.0, 360.0)
+        .cubic_to(590.0, 360.0, 600.0, 390.0, 600.0, 390.0)
+        .cubic_to(600.0, 390.0, 610.0, 360.0, 640.0, 360.0)
+        .cubic_to(720.0, 360.0, 720.0, 480.0, 600.0, 560.0)
+        .close();
+    let plasma = |x: f32, y: f32| {
+        let v = ((x * 0.05).sin() + (y * 0.07).cos() + ((x + y) * 0.03).sin()) / 3.0;
+        let c = ((v * 0.5 + 0.5) * 255.0) as u32;
+        0xff_000000 | c << 16 | (255 - c)
+    };
+    renderer.fill_path_aa_paint(&heart, FillRule::NonZero, &plasma);
+    renderer.save_to_ppm_file(file).unwrap();
+    renderer.save_to_bmp_file("output/paint.bmp").unwrap();
+    renderer.save_to_tga_file("output/paint.tga", true).unwrap();
+    renderer.save_to_qoi_file("output/paint.qoi").unwrap();
+    renderer.save_to_farbfeld_file("output/paint.ff").unwrap();
+}
+
+fn load_ppm_example() {
+    // written by rounded_rect_example
+    let image = Image::load_ppm_file("output/rounded_rect.ppm").unwrap();
+    let mut buffer = [0u32; BUFFER_LEN];
+    let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
+    let file = "output/load_ppm.ppm";
+    renderer.fill(BACKGROUND_COLOR);
+    let pattern = image.pattern().offset(-100, -100);
+    renderer.fill_circle_aa_paint(400, 300, 250, &pattern);
+    renderer.save_to_ppm_file(file).unwrap();
+}
+
 fn triangle_example() {
     let mut buffer = [0u32; BUFFER_LEN];
     let mut renderer = Renderer::new(&mut buffer, WIDTH, HEIGHT);
@@ -267,14 +695,52 @@ fn sub_canvas_example() {
     let mut sub_canvas =
         Renderer::sub_canvas(renderer.get_buffer_mut(), 15, 15, 200, 100, WIDTH, HEIGHT);
     sub_canvas.copy(&renderer2);
+    sub_canvas
+        .save_to_ppm_file("output/sub_canvas_only.ppm")
+        .unwrap();
 
     renderer.save_to_ppm_file(file).unwrap();
 }
 
+fn pixel_format_example() {
+    // the same drawing in 16 bit and grayscale buffers, blended in their own format
+    fn draw<F: PixelFormat>(renderer: &mut Renderer<'_, F>) {
+        renderer.fill(BACKGROUND_COLOR);
+        renderer.fill_circle_aa(300, 300, 200, RED);
+        renderer.begin_blending();
+        renderer.fill_circle_aa(500, 300, 200, 0x80_ff0000);
+        renderer.fill_rect(200, 250, 400, 100, 0x80_00ff00);
+        renderer.end_blending();
+    }
+    let mut buffer = vec![0u16; BUFFER_LEN];
+    let mut renderer = Renderer::with_format(&mut buffer, WIDTH, HEIGHT, Rgb565);
+    draw(&mut renderer);
+    renderer
+        .save_to_ppm_file("output/pixel_format_rgb565.ppm")
+        .unwrap();
+    let mut buffer = vec![0u8; BUFFER_LEN];
+    let mut renderer = Renderer::with_format(&mut buffer, WIDTH, HEIGHT, Gray8);
+    draw(&mut renderer);
+    renderer
+        .save_to_ppm_file("output/pixel_format_gray8.ppm")
+        .unwrap();
+}
+
 fn main() {
     checker_example();
     circle_example();
     line_example();
+    line_aa_example();
+    thick_line_example();
+    outline_example();
+    polygon_example();
+    ellipse_example();
+    bezier_example();
+    path_example();
+    rounded_rect_example();
+    gradient_example();
+    paint_example();
+    load_ppm_example();
     triangle_example();
     rect_example();
     alpha_example();
@@ -283,4 +749,5 @@ fn main() {
     text_example();
     sub_canvas_example();
     triangle_mix_example();
+    pixel_format_example();
 }
@@ -0,0 +1,169 @@
+// how the pixels of the buffer a renderer draws to are stored,
+// colors are always given in the 0xAABBGGRR layout and converted when they are written,
+// names follow SDL, the channels are listed from the most to the least significant bits
+use crate::renderer::{alpha_part, blue_part, green_part, red_part, rgba2color};
+
+pub trait PixelFormat: Copy + Default {
+    type Pixel: Copy;
+    fn from_rgba(color: u32) -> Self::Pixel;
+    fn to_rgba(pixel: Self::Pixel) -> u32;
+    // draws color over the pixel, weighted by the alpha of color,
+    // the alpha of the pixel, when it has one, is kept
+    fn blend(pixel: &mut Self::Pixel, color: u32);
+    // the pixels themselves when they already are in the 0xAABBGGRR layout,
+    // so they can be read without converting them
+    fn as_rgba(_pixels: &[Self::Pixel]) -> Option<&[u32]> {
+        None
+    }
+}
+
+// 0xAABBGGRR, R, G, B, A in little endian memory, the default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Abgr8888;
+impl PixelFormat for Abgr8888 {
+    type Pixel = u32;
+    #[inline]
+    fn from_rgba(color: u32) -> u32 {
+        color
+    }
+    #[inline]
+    fn to_rgba(pixel: u32) -> u32 {
+        pixel
+    }
+    #[inline]
+    fn blend(pixel: &mut u32, color: u32) {
+        blend_8888(pixel, color, 24);
+    }
+    #[inline]
+    fn as_rgba(pixels: &[u32]) -> Option<&[u32]> {
+        Some(pixels)
+    }
+}
+
+// 0xAARRGGBB, B, G, R, A in little endian memory, the usual 32 bit framebuffer layout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Argb8888;
+impl PixelFormat for Argb8888 {
+    type Pixel = u32;
+    #[inline]
+    fn from_rgba(color: u32) -> u32 {
+        color & 0xff00ff00 | (color & 0xff) << 16 | (color >> 16) & 0xff
+    }
+    #[inline]
+    fn to_rgba(pixel: u32) -> u32 {
+        // swapping red and blue goes both ways
+        Self::from_rgba(pixel)
+    }
+    #[inline]
+    fn blend(pixel: &mut u32, color: u32) {
+        blend_8888(pixel, Self::from_rgba(color), 24);
+    }
+}
+
+// 0xBBGGRRAA, A, R, G, B in little endian memory
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bgra8888;
+impl PixelFormat for Bgra8888 {
+    type Pixel = u32;
+    #[inline]
+    fn from_rgba(color: u32) -> u32 {
+        color.rotate_left(8)
+    }
+    #[inline]
+    fn to_rgba(pixel: u32) -> u32 {
+        pixel.rotate_right(8)
+    }
+    #[inline]
+    fn blend(pixel: &mut u32, color: u32) {
+        blend_8888(pixel, Self::from_rgba(color), 0);
+    }
+}
+
+// 5 bits of red, 6 of green and 5 of blue, no alpha, common on small LCDs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb565;
+impl PixelFormat for Rgb565 {
+    type Pixel = u16;
+    #[inline]
+    fn from_rgba(color: u32) -> u16 {
+        pack_565(red_part(color), green_part(color), blue_part(color))
+    }
+    #[inline]
+    fn to_rgba(pixel: u16) -> u32 {
+        let [r, g, b] = unpack_565(pixel);
+        rgba2color(r, g, b, 0xff)
+    }
+    // blended with 8 bit channels, rounding to 5 and 6 bits only once
+    #[inline]
+    fn blend(pixel: &mut u16, color: u32) {
+        let a = alpha_part(color);
+        let [r, g, b] = unpack_565(*pixel);
+        *pixel = pack_565(
+            blend_channel(r, red_part(color), a),
+            blend_channel(g, green_part(color), a),
+            blend_channel(b, blue_part(color), a),
+        );
+    }
+}
+
+// 8 bit luma, no alpha
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gray8;
+impl PixelFormat for Gray8 {
+    type Pixel = u8;
+    // BT.601 weights, in 256ths
+    #[inline]
+    fn from_rgba(color: u32) -> u8 {
+        ((red_part(color) * 77 + green_part(color) * 150 + blue_part(color) * 29 + 128) >> 8) as u8
+    }
+    #[inline]
+    fn to_rgba(pixel: u8) -> u32 {
+        let v = pixel as u32;
+        rgba2color(v, v, v, 0xff)
+    }
+    #[inline]
+    fn blend(pixel: &mut u8, color: u32) {
+        *pixel = blend_channel(
+            *pixel as u32,
+            Self::from_rgba(color) as u32,
+            alpha_part(color),
+        ) as u8;
+    }
+}
+
+// every byte but the alpha one, at alpha_shift, is blended,
+// color is already in the layout of the pixel
+#[inline]
+fn blend_8888(pixel: &mut u32, color: u32, alpha_shift: u32) {
+    let a = (color >> alpha_shift) & 0xff;
+    let mut blended = *pixel & (0xff << alpha_shift);
+    for shift in [0, 8, 16, 24] {
+        if shift != alpha_shift {
+            let channel = blend_channel((*pixel >> shift) & 0xff, (color >> shift) & 0xff, a);
+            blended |= channel << shift;
+        }
+    }
+    *pixel = blended;
+}
+
+#[inline]
+fn blend_channel(bottom: u32, top: u32, alpha: u32) -> u32 {
+    (top * alpha + bottom * (0xff - alpha)) / 0xff
+}
+
+#[inline]
+fn pack_565(r: u32, g: u32, b: u32) -> u16 {
+    // rounded to the nearest of the 32 or 64 levels
+    let r = (r * 31 + 127) / 255;
+    let g = (g * 63 + 127) / 255;
+    let b = (b * 31 + 127) / 255;
+    (r << 11 | g << 5 | b) as u16
+}
+
+// the high bits are repeated in the low ones, so 0x1f becomes 0xff
+#[inline]
+fn unpack_565(pixel: u16) -> [u32; 3] {
+    let pixel = pixel as u32;
+    let (r, g, b) = (pixel >> 11, (pixel >> 5) & 0x3f, pixel & 0x1f);
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
@@ -1,28 +1,67 @@
 #![allow(clippy::too_many_arguments)]
 
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
 use std::{
     fs::File,
     io::{BufWriter, Write},
-    path::Path,
 };
 
 use phf::phf_map;
 
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::pixel_format::{Abgr8888, PixelFormat};
+
+#[cfg(feature = "std")]
+use crate::image::{self, ImageFormat};
+#[cfg(not(feature = "std"))]
+use crate::math::FloatExt;
+
 const AA_RES: i32 = 2;
 const AA_PADDING: f32 = 1f32 / (AA_RES + 1) as f32;
 
-pub struct Renderer<'b> {
-    buffer: &'b mut [u32],
+// draws into a buffer of pixels in the format F, 0xAABBGGRR by default
+pub struct Renderer<'b, F: PixelFormat = Abgr8888> {
+    buffer: &'b mut [F::Pixel],
     pub width: u32,
     pub height: u32,
     pub stride: u32,
     draw_horizontal_line_unchecked_fn: fn(&mut Self, x0: u32, x1: u32, y: u32, color: u32),
     draw_pixel_unchecked_fn: fn(&mut Self, x: u32, y: u32, color: u32),
-    copy_fn: fn(this: &mut Self, source: &Self),
-    aa_color_fn: fn(count: u8, color: u32) -> u32,
+    copy_fn: fn(this: &mut Self, source: &[F::Pixel], width: u32, height: u32, stride: u32),
+    aa_color_fn: fn(t: f32, color: u32) -> u32,
 }
 impl<'b> Renderer<'b> {
     pub fn new(buffer: &'b mut [u32], width: u32, height: u32) -> Self {
+        Self::with_format(buffer, width, height, Abgr8888)
+    }
+    // TODO: when the borrower checker get smarter, receive &'b mut self
+    // now use let sub = Renderer::sub_canvas(renderer.get_buffer_mut(), 15, 15, 50, 50, BUFFER_WIDTH, BUFFER_HEIGHT);
+    pub fn sub_canvas(
+        buffer: &'b mut [u32],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buffer_width: u32,
+        buffer_height: u32,
+    ) -> Self {
+        Self::sub_canvas_with_format(
+            buffer,
+            x,
+            y,
+            width,
+            height,
+            buffer_width,
+            buffer_height,
+            Abgr8888,
+        )
+    }
+}
+impl<'b, F: PixelFormat> Renderer<'b, F> {
+    // the format only picks F, e.g. Renderer::with_format(framebuffer, 320, 240, Rgb565)
+    pub fn with_format(buffer: &'b mut [F::Pixel], width: u32, height: u32, _format: F) -> Self {
         assert_eq!((width * height) as usize, buffer.len());
         const BLENDING_ENABLED: bool = false;
         Self {
@@ -38,16 +77,15 @@ impl<'b> Renderer<'b> {
             aa_color_fn: aa_color::<BLENDING_ENABLED>,
         }
     }
-    // TODO: when the borrower checker get smarter, receive &'b mut self
-    // now use let sub = Renderer::sub_canvas(renderer.get_buffer_mut(), 15, 15, 50, 50, BUFFER_WIDTH, BUFFER_HEIGHT);
-    pub fn sub_canvas(
-        buffer: &'b mut [u32],
+    pub fn sub_canvas_with_format(
+        buffer: &'b mut [F::Pixel],
         x: u32,
         y: u32,
         width: u32,
         height: u32,
         buffer_width: u32,
         buffer_height: u32,
+        _format: F,
     ) -> Self {
         assert!(x < buffer_width);
         assert!(x + width <= buffer_width);
@@ -69,20 +107,20 @@ impl<'b> Renderer<'b> {
             aa_color_fn: aa_color::<BLENDING_ENABLED>,
         }
     }
-    pub fn get_buffer(&self) -> &[u32] {
+    pub fn get_buffer(&self) -> &[F::Pixel] {
         self.buffer
     }
-    pub fn get_buffer_mut(&mut self) -> &mut [u32] {
+    pub fn get_buffer_mut(&mut self) -> &mut [F::Pixel] {
         self.buffer
     }
-    pub fn get_row_unchecked(&self, y: u32) -> &[u32] {
+    pub fn get_row_unchecked(&self, y: u32) -> &[F::Pixel] {
         let start = y * self.stride;
         let end = start + self.width;
         let start = start as usize;
         let end = end as usize;
         &self.buffer[start..end]
     }
-    pub fn get_row_mut_unchecked(&mut self, y: u32) -> &mut [u32] {
+    pub fn get_row_mut_unchecked(&mut self, y: u32) -> &mut [F::Pixel] {
         let start = y * self.stride;
         let end = start + self.width;
         let start = start as usize;
@@ -103,32 +141,55 @@ impl<'b> Renderer<'b> {
     }
     #[inline]
     pub fn copy(&mut self, source: &Self) {
-        (self.copy_fn)(self, source);
+        (self.copy_fn)(
+            self,
+            source.buffer,
+            source.width,
+            source.height,
+            source.stride,
+        );
     }
-    fn m_copy<const BLENDING_ENABLED: bool>(&mut self, source: &Self) {
+    #[inline]
+    pub fn copy_canvas(&mut self, source: &Canvas<F>) {
+        (self.copy_fn)(
+            self,
+            source.pixels(),
+            source.width(),
+            source.height(),
+            source.width(),
+        );
+    }
+    // source is scaled to the size of the canvas, nearest neighbour
+    fn m_copy<const BLENDING_ENABLED: bool>(
+        &mut self,
+        source: &[F::Pixel],
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) {
         let w = self.width as usize;
         let h = self.height as usize;
-        let sw = source.width as usize;
-        let sh = source.height as usize;
+        let sw = width as usize;
+        let sh = height as usize;
         for y in 0..h {
             let sy = y * sh / h;
-            let start = sy * sw;
+            let start = sy * stride as usize;
             self.get_row_mut_unchecked(y as u32)
                 .iter_mut()
                 .enumerate()
                 .for_each(|(x, pixel)| {
                     let sx = x * sw / w;
                     if BLENDING_ENABLED {
-                        blend_color(pixel, source.buffer[start + sx]);
+                        F::blend(pixel, F::to_rgba(source[start + sx]));
                     } else {
-                        *pixel = source.buffer[start + sx];
+                        *pixel = source[start + sx];
                     }
                 });
         }
     }
-    fn draw_horizontal_line(&mut self, mut x0: i32, mut x1: i32, y: i32, color: u32) {
+    fn draw_horizontal_line(&mut self, mut x0: i32, mut x1: i32, y: i32, paint: &impl Paint) {
         if x1 < x0 {
-            std::mem::swap(&mut x0, &mut x1);
+            core::mem::swap(&mut x0, &mut x1);
         }
         if x1 < 0 {
             return;
@@ -141,11 +202,28 @@ impl<'b> Renderer<'b> {
             let y = y as u32;
             let x0 = x0.max(0) as u32;
             let xn = (x1 as u32).min(self.width - 1);
+            self.m_draw_span(x0, xn, y, paint);
+        }
+    }
+    // draw_horizontal_line_unchecked, with the color of every pixel taken from the paint
+    fn m_draw_span(&mut self, x0: u32, xn: u32, y: u32, paint: &impl Paint) {
+        if let Some(color) = paint.solid_color() {
             self.draw_horizontal_line_unchecked(x0, xn, y, color);
+        } else {
+            for x in x0..=xn {
+                self.draw_pixel_unchecked(x, y, paint.color_at(x as f32, y as f32));
+            }
         }
     }
     #[inline]
-    pub fn draw_horizontal_line_unchecked(&mut self, x0: u32, x1: u32, y: u32, color: u32) {
+    pub fn draw_horizontal_line_unchecked(
+        &mut self,
+        x0: u32,
+        x1: u32,
+        y: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
         (self.draw_horizontal_line_unchecked_fn)(self, x0, x1, y, color);
     }
     fn m_draw_horizontal_line_unchecked<const BLENDING_ENABLED: bool>(
@@ -157,15 +235,15 @@ impl<'b> Renderer<'b> {
     ) {
         let start_i = (y * self.stride + x0) as usize;
         let end_i = (y * self.stride + xn) as usize;
-        self.buffer[start_i..=end_i].iter_mut().for_each(|pixel| {
-            if BLENDING_ENABLED {
-                blend_color(pixel, color);
-            } else {
-                *pixel = color;
-            }
-        });
+        let pixels = &mut self.buffer[start_i..=end_i];
+        if BLENDING_ENABLED {
+            pixels.iter_mut().for_each(|pixel| F::blend(pixel, color));
+        } else {
+            pixels.fill(F::from_rgba(color));
+        }
     }
-    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: impl Into<Color>) {
+        let color = color.into().to_u32();
         let (x0, y0, x1, y1) = if let Some(Line2D { x0, y0, x1, y1 }) = (Line2D {
             x0: x0 as f32,
             y0: y0 as f32,
@@ -190,10 +268,577 @@ impl<'b> Renderer<'b> {
             self.draw_pixel_unchecked(x as u32, y as u32, color)
         }
     }
-    pub fn fill(&mut self, color: u32) {
+    // pixel centers are at integer coordinates, so draw_line_aa(0.0, 0.0, 10.0, 0.0, ..)
+    // covers the same pixels as draw_line(0, 0, 10, 0, ..)
+    pub fn draw_line_aa(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: impl Into<Color>) {
+        let color = color.into().to_u32();
+        // clip one pixel outside the canvas so that the partially covered
+        // neighbours of the border pixels are still drawn
+        let Some(Line2D {
+            mut x0,
+            mut y0,
+            mut x1,
+            mut y1,
+        }) = (Line2D { x0, y0, x1, y1 }).box_clip(
+            -1f32,
+            -1f32,
+            self.width as f32,
+            self.height as f32,
+        )
+        else {
+            return;
+        };
+
+        // Xiaolin Wu
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            core::mem::swap(&mut x0, &mut y0);
+            core::mem::swap(&mut x1, &mut y1);
+        }
+        if x1 < x0 {
+            core::mem::swap(&mut x0, &mut x1);
+            core::mem::swap(&mut y0, &mut y1);
+        }
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let gradient = if dx == 0f32 { 1f32 } else { dy / dx };
+        let mut plot = |x: i32, y: i32, t: f32| {
+            if steep {
+                self.draw_pixel_aa(y, x, color, t);
+            } else {
+                self.draw_pixel_aa(x, y, color, t);
+            }
+        };
+
+        // first endpoint
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap0 = 1f32 - fract(x0 + 0.5);
+        let x_pixel0 = x_end as i32;
+        let y_pixel0 = y_end.floor() as i32;
+        let y_fract0 = fract(y_end);
+        let mut inter_y = y_end + gradient;
+
+        // second endpoint
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap1 = fract(x1 + 0.5);
+        let x_pixel1 = x_end as i32;
+        let y_pixel1 = y_end.floor() as i32;
+        let y_fract1 = fract(y_end);
+
+        if x_pixel0 == x_pixel1 {
+            // both endpoints fall into the same column, draw it once
+            let x_gap = dx;
+            plot(x_pixel0, y_pixel0, (1f32 - y_fract0) * x_gap);
+            plot(x_pixel0, y_pixel0 + 1, y_fract0 * x_gap);
+            return;
+        }
+        plot(x_pixel0, y_pixel0, (1f32 - y_fract0) * x_gap0);
+        plot(x_pixel0, y_pixel0 + 1, y_fract0 * x_gap0);
+        plot(x_pixel1, y_pixel1, (1f32 - y_fract1) * x_gap1);
+        plot(x_pixel1, y_pixel1 + 1, y_fract1 * x_gap1);
+
+        for x in x_pixel0 + 1..x_pixel1 {
+            let y = inter_y.floor();
+            let t = inter_y - y;
+            plot(x, y as i32, 1f32 - t);
+            plot(x, y as i32 + 1, t);
+            inter_y += gradient;
+        }
+    }
+    pub fn draw_thick_line(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        let shapes = stroke_polyline(&[(x0, y0), (x1, y1)], false, width, cap, LineJoin::Miter);
+        self.m_fill_shapes::<false>(&shapes, &color.into());
+    }
+    pub fn draw_thick_line_aa(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        let shapes = stroke_polyline(&[(x0, y0), (x1, y1)], false, width, cap, LineJoin::Miter);
+        self.m_fill_shapes::<true>(&shapes, &color.into());
+    }
+    pub fn draw_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        width: f32,
+        cap: LineCap,
+        join: LineJoin,
+        color: impl Into<Color>,
+    ) {
+        let shapes = stroke_polyline(points, false, width, cap, join);
+        self.m_fill_shapes::<false>(&shapes, &color.into());
+    }
+    pub fn draw_polyline_aa(
+        &mut self,
+        points: &[(f32, f32)],
+        width: f32,
+        cap: LineCap,
+        join: LineJoin,
+        color: impl Into<Color>,
+    ) {
+        let shapes = stroke_polyline(points, false, width, cap, join);
+        self.m_fill_shapes::<true>(&shapes, &color.into());
+    }
+    // fill the union of the shapes, every pixel is drawn at most once,
+    // so overlapping shapes are not blended twice
+    fn m_fill_shapes<const AA_ENABLED: bool>(&mut self, shapes: &[Shape], paint: &impl Paint) {
+        if shapes.is_empty() {
+            return;
+        }
+        let (mut x_min, mut y_min, mut x_max, mut y_max) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for shape in shapes {
+            let (x0, y0, x1, y1) = shape.bounding_box();
+            x_min = x_min.min(x0);
+            y_min = y_min.min(y0);
+            x_max = x_max.max(x1);
+            y_max = y_max.max(y1);
+        }
+        let (x_min, y_min) = (x_min.floor() as i32, y_min.floor() as i32);
+        let (x_max, y_max) = (x_max.floor() as i32, y_max.floor() as i32);
+        let Some(((x_min, y_min), (x_max, y_max))) = normalize_rect(
+            x_min,
+            y_min,
+            x_max.saturating_sub(x_min).saturating_add(1),
+            y_max.saturating_sub(y_min).saturating_add(1),
+            self.width,
+            self.height,
+        ) else {
+            return;
+        };
+        // one bit for every sample in a pixel
+        let mut row_masks = vec![0u32; (x_max - x_min + 1) as usize];
+        for y in y_min..=y_max {
+            row_masks.iter_mut().for_each(|mask| *mask = 0);
+            let row_top = y as f32;
+            let row_bottom = row_top + 1f32;
+            for shape in shapes {
+                let (x0, y0, x1, y1) = shape.bounding_box();
+                if y1 < row_top || y0 > row_bottom {
+                    continue;
+                }
+                let x0 = (x0.floor().max(x_min as f32) as u32).min(x_max);
+                let x1 = (x1.floor().max(x_min as f32) as u32).min(x_max);
+                for x in x0..=x1 {
+                    let mask = &mut row_masks[(x - x_min) as usize];
+                    if AA_ENABLED {
+                        for sub_x in 1..=AA_RES {
+                            for sub_y in 1..=AA_RES {
+                                let sx = x as f32 + sub_x as f32 * AA_PADDING;
+                                let sy = row_top + sub_y as f32 * AA_PADDING;
+                                if shape.contains(sx, sy) {
+                                    *mask |= 1 << ((sub_x - 1) * AA_RES + sub_y - 1);
+                                }
+                            }
+                        }
+                    } else if shape.contains(x as f32 + 0.5, row_top + 0.5) {
+                        *mask = 1;
+                    }
+                }
+            }
+            for (i, &mask) in row_masks.iter().enumerate() {
+                if mask == 0 {
+                    continue;
+                }
+                let x = x_min + i as u32;
+                let color = paint.color_at(x as f32, y as f32);
+                if AA_ENABLED {
+                    let t = mask.count_ones() as f32 / (AA_RES * AA_RES) as f32;
+                    let color = self.aa_color(t, color);
+                    self.m_draw_pixel_unchecked::<true>(x, y, color);
+                } else {
+                    self.draw_pixel_unchecked(x, y, color);
+                }
+            }
+        }
+    }
+    pub fn draw_quad_bezier(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        self.draw_quad_bezier_with_tolerance(
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            width,
+            cap,
+            FLATTEN_TOLERANCE,
+            color,
+        );
+    }
+    // tolerance is the largest distance in pixels between the curve and the drawn polyline
+    pub fn draw_quad_bezier_with_tolerance(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        width: f32,
+        cap: LineCap,
+        tolerance: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let mut points = vec![(x0, y0)];
+        flatten_quad_bezier(&mut points, x0, y0, x1, y1, x2, y2, tolerance);
+        self.draw_polyline(&points, width, cap, LineJoin::Miter, color);
+    }
+    pub fn draw_quad_bezier_aa(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        self.draw_quad_bezier_aa_with_tolerance(
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            width,
+            cap,
+            FLATTEN_TOLERANCE,
+            color,
+        );
+    }
+    pub fn draw_quad_bezier_aa_with_tolerance(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        width: f32,
+        cap: LineCap,
+        tolerance: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let mut points = vec![(x0, y0)];
+        flatten_quad_bezier(&mut points, x0, y0, x1, y1, x2, y2, tolerance);
+        self.draw_polyline_aa(&points, width, cap, LineJoin::Miter, color);
+    }
+    pub fn draw_cubic_bezier(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        self.draw_cubic_bezier_with_tolerance(
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            width,
+            cap,
+            FLATTEN_TOLERANCE,
+            color,
+        );
+    }
+    pub fn draw_cubic_bezier_with_tolerance(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+        width: f32,
+        cap: LineCap,
+        tolerance: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let mut points = vec![(x0, y0)];
+        flatten_cubic_bezier(&mut points, x0, y0, x1, y1, x2, y2, x3, y3, tolerance);
+        self.draw_polyline(&points, width, cap, LineJoin::Miter, color);
+    }
+    pub fn draw_cubic_bezier_aa(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+        width: f32,
+        cap: LineCap,
+        color: impl Into<Color>,
+    ) {
+        self.draw_cubic_bezier_aa_with_tolerance(
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            width,
+            cap,
+            FLATTEN_TOLERANCE,
+            color,
+        );
+    }
+    pub fn draw_cubic_bezier_aa_with_tolerance(
+        &mut self,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+        width: f32,
+        cap: LineCap,
+        tolerance: f32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let mut points = vec![(x0, y0)];
+        flatten_cubic_bezier(&mut points, x0, y0, x1, y1, x2, y2, x3, y3, tolerance);
+        self.draw_polyline_aa(&points, width, cap, LineJoin::Miter, color);
+    }
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule, color: impl Into<Color>) {
+        self.fill_path_paint(path, rule, &color.into());
+    }
+    pub fn fill_path_paint(&mut self, path: &Path, rule: FillRule, paint: &impl Paint) {
+        let contours = path.flatten(FLATTEN_TOLERANCE);
+        let contours: Vec<&[(f32, f32)]> = contours.iter().map(|(c, _)| c.as_slice()).collect();
+        self.m_fill_edges::<false>(polygon_edges(&contours), rule, paint);
+    }
+    pub fn fill_path_aa(&mut self, path: &Path, rule: FillRule, color: impl Into<Color>) {
+        self.fill_path_aa_paint(path, rule, &color.into());
+    }
+    pub fn fill_path_aa_paint(&mut self, path: &Path, rule: FillRule, paint: &impl Paint) {
+        let contours = path.flatten(FLATTEN_TOLERANCE);
+        let contours: Vec<&[(f32, f32)]> = contours.iter().map(|(c, _)| c.as_slice()).collect();
+        self.m_fill_edges::<true>(polygon_edges(&contours), rule, paint);
+    }
+    pub fn stroke_path(
+        &mut self,
+        path: &Path,
+        width: f32,
+        cap: LineCap,
+        join: LineJoin,
+        color: impl Into<Color>,
+    ) {
+        let shapes = path.stroke(width, cap, join);
+        self.m_fill_shapes::<false>(&shapes, &color.into());
+    }
+    pub fn stroke_path_aa(
+        &mut self,
+        path: &Path,
+        width: f32,
+        cap: LineCap,
+        join: LineJoin,
+        color: impl Into<Color>,
+    ) {
+        let shapes = path.stroke(width, cap, join);
+        self.m_fill_shapes::<true>(&shapes, &color.into());
+    }
+    pub fn fill_polygon(&mut self, points: &[(f32, f32)], rule: FillRule, color: impl Into<Color>) {
+        self.fill_polygon_paint(points, rule, &color.into());
+    }
+    pub fn fill_polygon_paint(
+        &mut self,
+        points: &[(f32, f32)],
+        rule: FillRule,
+        paint: &impl Paint,
+    ) {
+        let edges = polygon_edges(&[points]);
+        self.m_fill_edges::<false>(edges, rule, paint);
+    }
+    pub fn fill_polygon_aa(
+        &mut self,
+        points: &[(f32, f32)],
+        rule: FillRule,
+        color: impl Into<Color>,
+    ) {
+        self.fill_polygon_aa_paint(points, rule, &color.into());
+    }
+    pub fn fill_polygon_aa_paint(
+        &mut self,
+        points: &[(f32, f32)],
+        rule: FillRule,
+        paint: &impl Paint,
+    ) {
+        let edges = polygon_edges(&[points]);
+        self.m_fill_edges::<true>(edges, rule, paint);
+    }
+    // scanline
+    fn m_fill_edges<const AA_ENABLED: bool>(
+        &mut self,
+        mut edges: Vec<Edge>,
+        rule: FillRule,
+        paint: &impl Paint,
+    ) {
+        if edges.is_empty() {
+            return;
+        }
+        let (mut x_min, mut y_min, mut x_max, mut y_max) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for edge in &edges {
+            x_min = x_min.min(edge.x0).min(edge.x1);
+            x_max = x_max.max(edge.x0).max(edge.x1);
+            y_min = y_min.min(edge.y0);
+            y_max = y_max.max(edge.y1);
+        }
+        let (x_min, y_min) = (x_min.floor() as i32, y_min.floor() as i32);
+        let (x_max, y_max) = (x_max.floor() as i32, y_max.floor() as i32);
+        let Some(((x_min, y_min), (x_max, y_max))) = normalize_rect(
+            x_min,
+            y_min,
+            x_max.saturating_sub(x_min).saturating_add(1),
+            y_max.saturating_sub(y_min).saturating_add(1),
+            self.width,
+            self.height,
+        ) else {
+            return;
+        };
+
+        edges.sort_by(|a, b| a.y0.total_cmp(&b.y0));
+        let mut next_edge = 0;
+        let mut active_edges: Vec<Edge> = Vec::new();
+        let mut crossings = Vec::new();
+        let mut spans = Vec::new();
+        let mut row_counts = vec![
+            0u8;
+            if AA_ENABLED {
+                (x_max - x_min + 1) as usize
+            } else {
+                0
+            }
+        ];
+        for y in y_min..=y_max {
+            let row_top = y as f32;
+            let row_bottom = row_top + 1f32;
+            while next_edge < edges.len() && edges[next_edge].y0 < row_bottom {
+                active_edges.push(edges[next_edge]);
+                next_edge += 1;
+            }
+            active_edges.retain(|edge| edge.y1 > row_top);
+            if active_edges.is_empty() {
+                continue;
+            }
+
+            if !AA_ENABLED {
+                scanline_spans(
+                    &active_edges,
+                    row_top + 0.5,
+                    rule,
+                    &mut crossings,
+                    &mut spans,
+                );
+                for &(x0, x1) in &spans {
+                    // the pixels whose center is in [x0, x1)
+                    let x0 = ((x0 - 0.5).ceil().max(x_min as f32) as u32).min(x_max + 1);
+                    let x1 = ((x1 - 0.5).ceil().max(x_min as f32) as u32).min(x_max + 1);
+                    if x0 < x1 {
+                        self.m_draw_span(x0, x1 - 1, y, paint);
+                    }
+                }
+                continue;
+            }
+
+            row_counts.iter_mut().for_each(|count| *count = 0);
+            for sub_y in 1..=AA_RES {
+                let sy = row_top + sub_y as f32 * AA_PADDING;
+                scanline_spans(&active_edges, sy, rule, &mut crossings, &mut spans);
+                for &(x0, x1) in &spans {
+                    let px0 = (x0.floor().max(x_min as f32) as u32).min(x_max);
+                    let px1 = (x1.floor().max(x_min as f32) as u32).min(x_max);
+                    for x in px0..=px1 {
+                        for sub_x in 1..=AA_RES {
+                            let sx = x as f32 + sub_x as f32 * AA_PADDING;
+                            if x0 <= sx && sx < x1 {
+                                row_counts[(x - x_min) as usize] += 1;
+                            }
+                        }
+                    }
+                }
+            }
+            for (i, &count) in row_counts.iter().enumerate() {
+                if count > 0 {
+                    let x = x_min + i as u32;
+                    let t = count as f32 / (AA_RES * AA_RES) as f32;
+                    let color = self.aa_color(t, paint.color_at(x as f32, y as f32));
+                    self.m_draw_pixel_unchecked::<true>(x, y, color);
+                }
+            }
+        }
+    }
+    pub fn fill(&mut self, color: impl Into<Color>) {
         self.fill_rect(0, 0, self.width as i32, self.height as i32, color);
     }
+    pub fn fill_paint(&mut self, paint: &impl Paint) {
+        self.fill_rect_paint(0, 0, self.width as i32, self.height as i32, paint);
+    }
     pub fn fill_triangle(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_triangle_paint(x0, y0, x1, y1, x2, y2, &color.into());
+    }
+    pub fn fill_triangle_paint(
         &mut self,
         mut x0: i32,
         mut y0: i32,
@@ -201,10 +846,10 @@ impl<'b> Renderer<'b> {
         mut y1: i32,
         mut x2: i32,
         mut y2: i32,
-        color: u32,
+        paint: &impl Paint,
     ) {
         sort_by_y(&mut x0, &mut y0, &mut x1, &mut y1, &mut x2, &mut y2);
-        self.draw_pixel(x2, y2, color);
+        self.draw_pixel(x2, y2, paint.color_at(x2 as f32, y2 as f32));
         let mut ray0 = Ray::new(x0, y0, x1, y1);
         let mut ray1 = Ray::new(x1, y1, x2, y2);
         let mut ray2 = Ray::new(x0, y0, x2, y2);
@@ -220,7 +865,7 @@ impl<'b> Renderer<'b> {
                 while cy2 != row {
                     (cx2, cy2) = ray2.next_xy();
                 }
-                self.draw_horizontal_line(cx0, cx2, row, color);
+                self.draw_horizontal_line(cx0, cx2, row, paint);
                 row += 1;
             }
         }
@@ -232,7 +877,7 @@ impl<'b> Renderer<'b> {
                 while cy2 != row {
                     (cx2, cy2) = ray2.next_xy();
                 }
-                self.draw_horizontal_line(cx1, cx2, row, color);
+                self.draw_horizontal_line(cx1, cx2, row, paint);
                 row += 1;
             }
         }
@@ -245,7 +890,19 @@ impl<'b> Renderer<'b> {
         y1: i32,
         x2: i32,
         y2: i32,
-        color: u32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_triangle_aa_paint(x0, y0, x1, y1, x2, y2, &color.into());
+    }
+    pub fn fill_triangle_aa_paint(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        paint: &impl Paint,
     ) {
         let ((x_min, y_min), (x_max, y_max)) = triangle_bunding_box(x0, y0, x1, y1, x2, y2);
         if let Some(((x_min, y_min), (x_max, y_max))) = normalize_rect(
@@ -264,25 +921,79 @@ impl<'b> Renderer<'b> {
             let y2 = y2 as f32 + 0.5;
             for y in y_min..=y_max {
                 for x in x_min..=x_max {
-                    self.draw_pixel_unchecked_aa(x, y, color, |x, y| {
+                    self.draw_pixel_unchecked_aa(x, y, paint, |x, y| {
                         xy_in_triangle(x, y, x0, y0, x1, y1, x2, y2)
                     });
                 }
             }
         }
     }
+    // the outline is centered on the edges of the triangle
+    pub fn draw_triangle(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let points = [
+            (x0 as f32, y0 as f32),
+            (x1 as f32, y1 as f32),
+            (x2 as f32, y2 as f32),
+        ];
+        let shapes = stroke_polyline(
+            &points,
+            true,
+            thickness as f32,
+            LineCap::Butt,
+            LineJoin::Miter,
+        );
+        self.m_fill_shapes::<false>(&shapes, &color);
+    }
+    pub fn draw_triangle_aa(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        let points = [
+            (x0 as f32, y0 as f32),
+            (x1 as f32, y1 as f32),
+            (x2 as f32, y2 as f32),
+        ];
+        let shapes = stroke_polyline(
+            &points,
+            true,
+            thickness as f32,
+            LineCap::Butt,
+            LineJoin::Miter,
+        );
+        self.m_fill_shapes::<true>(&shapes, &color);
+    }
     pub fn fill_triangle_mix(
         &mut self,
         mut x0: i32,
         mut y0: i32,
-        mut c0: u32,
+        c0: impl Into<Color>,
         mut x1: i32,
         mut y1: i32,
-        mut c1: u32,
+        c1: impl Into<Color>,
         mut x2: i32,
         mut y2: i32,
-        mut c2: u32,
+        c2: impl Into<Color>,
     ) {
+        let (mut c0, mut c1, mut c2) = (c0.into().to_u32(), c1.into().to_u32(), c2.into().to_u32());
         sort_by_y3(
             &mut x0, &mut y0, &mut c0, &mut x1, &mut y1, &mut c1, &mut x2, &mut y2, &mut c2,
         );
@@ -310,7 +1021,7 @@ impl<'b> Renderer<'b> {
                 }
                 // self.draw_horizontal_line(cx0, cx2, row, color);
                 if cx2 < cx0 {
-                    std::mem::swap(&mut cx0, &mut cx2);
+                    core::mem::swap(&mut cx0, &mut cx2);
                 }
                 for x in cx0..cx2 {
                     let (u, v, w) = barycentric(x as f32, row as f32, x0, y0, x1, y1, x2, y2);
@@ -338,7 +1049,7 @@ impl<'b> Renderer<'b> {
                 }
                 // self.draw_horizontal_line(cx1, cx2, row, color);
                 if cx2 < cx1 {
-                    std::mem::swap(&mut cx1, &mut cx2);
+                    core::mem::swap(&mut cx1, &mut cx2);
                 }
                 for x in cx1..=cx2 {
                     let (u, v, w) = barycentric(x as f32, row as f32, x0, y0, x1, y1, x2, y2);
@@ -355,14 +1066,15 @@ impl<'b> Renderer<'b> {
         &mut self,
         x0: i32,
         y0: i32,
-        c0: u32,
+        c0: impl Into<Color>,
         x1: i32,
         y1: i32,
-        c1: u32,
+        c1: impl Into<Color>,
         x2: i32,
         y2: i32,
-        c2: u32,
+        c2: impl Into<Color>,
     ) {
+        let (c0, c1, c2) = (c0.into().to_u32(), c1.into().to_u32(), c2.into().to_u32());
         let ((x_min, y_min), (x_max, y_max)) = triangle_bunding_box(x0, y0, x1, y1, x2, y2);
         if let Some(((x_min, y_min), (x_max, y_max))) = normalize_rect(
             x_min,
@@ -393,11 +1105,15 @@ impl<'b> Renderer<'b> {
             }
         }
     }
-    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, r: u32, color: u32) {
+    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, r: u32, color: impl Into<Color>) {
+        self.fill_circle_paint(center_x, center_y, r, &color.into());
+    }
+    pub fn fill_circle_paint(&mut self, center_x: i32, center_y: i32, r: u32, paint: &impl Paint) {
         if r == 0 {
             return;
         }
         if r == 1 {
+            let color = paint.color_at(center_x as f32, center_y as f32);
             self.draw_pixel(center_x, center_y, color);
             return;
         }
@@ -411,74 +1127,97 @@ impl<'b> Renderer<'b> {
             return;
         }
 
-        // bresenham
-        // Taylor Expansion to get rid of sqrt
-        // and other approximation
-        let mut x0 = 0;
-        let mut y0 = r;
-        let mut last_y = y0;
-        let mut d = 3 - 2 * r;
-
-        // horizontal_center_line draw once
-        self.draw_horizontal_line(center_x - y0, center_x + y0, center_y, color);
-        if d < 0 {
-            d += 4 * x0 + 6;
-        } else {
-            d += 4 * (x0 - y0) + 10;
-            y0 -= 1;
-        }
-        x0 += 1;
-
-        // only formulate the arc above the line y=x in the first quadrant.
-        // iterate x
-        while y0 >= x0 {
-            // avoid draw multiple times
-            if y0 != last_y {
-                let last_x = x0 - 1;
-                self.draw_horizontal_line(
-                    center_x - last_x,
-                    center_x + last_x,
-                    center_y + last_y,
-                    color,
-                );
-                self.draw_horizontal_line(
-                    center_x - last_x,
-                    center_x + last_x,
-                    center_y - last_y,
-                    color,
-                );
-                last_y = y0;
+        for (dy, half_width) in (0..).zip(CircleRows::new(r)) {
+            let x0 = center_x - half_width;
+            let x1 = center_x + half_width;
+            self.draw_horizontal_line(x0, x1, center_y + dy, paint);
+            // horizontal_center_line draw once
+            if dy != 0 {
+                self.draw_horizontal_line(x0, x1, center_y - dy, paint);
             }
-            self.draw_horizontal_line(center_x - y0, center_x + y0, center_y + x0, color);
-            self.draw_horizontal_line(center_x - y0, center_x + y0, center_y - x0, color);
-            if d < 0 {
-                d += 4 * x0 + 6;
-            } else {
-                d += 4 * (x0 - y0) + 10;
-                y0 -= 1;
-            }
-            x0 += 1;
         }
-        // because we are always drawing last_y in the loop, so we miss one y
-        let last_x = x0 - 1;
-        if last_x == last_y {
-            // the missing y have been drawn by last x
+    }
+    // the outline grows inwards from r, so it covers the edge of fill_circle(center_x, center_y, r, ..)
+    pub fn draw_circle(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if thickness == 0 {
             return;
         }
-        self.draw_horizontal_line(
-            center_x - last_x,
-            center_x + last_x,
-            center_y + last_y,
-            color,
-        );
-        self.draw_horizontal_line(
-            center_x - last_x,
-            center_x + last_x,
-            center_y - last_y,
-            color,
-        );
+        if thickness >= r || r == 1 {
+            self.fill_circle(center_x, center_y, r, color);
+            return;
+        }
+        let r = r as i32;
+        let inner_r = r - thickness as i32;
+
+        if center_x + r < 0
+            || center_y + r < 0
+            || center_x - r >= self.width as i32
+            || center_y - r >= self.height as i32
+        {
+            return;
+        }
+
+        let mut outer = CircleRows::new(r).peekable();
+        let mut inner = CircleRows::new(inner_r);
+        for dy in 0..=r {
+            let outer_half_width = outer.next().unwrap();
+            // never leave a gap to the next row, so a thin outline stays connected
+            let inner_half_width = inner
+                .next()
+                .unwrap_or(-1)
+                .min(outer.peek().copied().unwrap_or(-1));
+            for y in [center_y + dy, center_y - dy] {
+                if inner_half_width < 0 {
+                    self.draw_horizontal_line(
+                        center_x - outer_half_width,
+                        center_x + outer_half_width,
+                        y,
+                        &color,
+                    );
+                } else if inner_half_width < outer_half_width {
+                    self.draw_horizontal_line(
+                        center_x - outer_half_width,
+                        center_x - inner_half_width - 1,
+                        y,
+                        &color,
+                    );
+                    self.draw_horizontal_line(
+                        center_x + inner_half_width + 1,
+                        center_x + outer_half_width,
+                        y,
+                        &color,
+                    );
+                }
+                if dy == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    pub fn fill_circle_aa(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_circle_aa_paint(center_x, center_y, r, &color.into());
     }
-    pub fn fill_circle_aa(&mut self, center_x: i32, center_y: i32, r: u32, color: u32) {
+    pub fn fill_circle_aa_paint(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        paint: &impl Paint,
+    ) {
         let r = r as i32;
         if let Some(((x0, y0), (x1, y1))) = normalize_rect(
             center_x - r,
@@ -493,7 +1232,7 @@ impl<'b> Renderer<'b> {
             let center_y = center_y as f32 + 0.5;
             for y in y0..=y1 {
                 for x in x0..=x1 {
-                    self.draw_pixel_unchecked_aa(x, y, color, |x, y| {
+                    self.draw_pixel_unchecked_aa(x, y, paint, |x, y| {
                         let dx = x - center_x;
                         let dy = y - center_y;
                         dx * dx + dy * dy <= r * r
@@ -502,14 +1241,333 @@ impl<'b> Renderer<'b> {
             }
         }
     }
-    pub fn fill_rect(&mut self, x0: i32, y0: i32, w: i32, h: i32, color: u32) {
+    pub fn draw_circle_aa(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if thickness == 0 {
+            return;
+        }
+        let inner_r = r.saturating_sub(thickness) as f32;
+        let r = r as i32;
+        if let Some(((x0, y0), (x1, y1))) = normalize_rect(
+            center_x - r,
+            center_y - r,
+            2 * r + 1,
+            2 * r + 1,
+            self.width,
+            self.height,
+        ) {
+            let r = r as f32;
+            let center_x = center_x as f32 + 0.5;
+            let center_y = center_y as f32 + 0.5;
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.draw_pixel_unchecked_aa(x, y, &color, |x, y| {
+                        let dx = x - center_x;
+                        let dy = y - center_y;
+                        let d = dx * dx + dy * dy;
+                        inner_r * inner_r < d && d <= r * r
+                    });
+                }
+            }
+        }
+    }
+    pub fn fill_ellipse(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        rx: u32,
+        ry: u32,
+        rotation: f32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_ellipse_paint(center_x, center_y, rx, ry, rotation, &color.into());
+    }
+    pub fn fill_ellipse_paint(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        rx: u32,
+        ry: u32,
+        rotation: f32,
+        paint: &impl Paint,
+    ) {
+        let ellipse = ellipse_shape(center_x, center_y, rx, ry, rotation);
+        self.m_fill_shapes::<false>(&[ellipse], paint);
+    }
+    pub fn fill_ellipse_aa(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        rx: u32,
+        ry: u32,
+        rotation: f32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_ellipse_aa_paint(center_x, center_y, rx, ry, rotation, &color.into());
+    }
+    pub fn fill_ellipse_aa_paint(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        rx: u32,
+        ry: u32,
+        rotation: f32,
+        paint: &impl Paint,
+    ) {
+        let ellipse = ellipse_shape(center_x, center_y, rx, ry, rotation);
+        self.m_fill_shapes::<true>(&[ellipse], paint);
+    }
+    // the arc covers the angles between start_angle and end_angle,
+    // and like draw_circle it grows inwards from r
+    pub fn draw_arc(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if thickness == 0 {
+            return;
+        }
+        let inner_r = r.saturating_sub(thickness);
+        let sector = sector_shape(center_x, center_y, inner_r, r, start_angle, end_angle);
+        self.m_fill_shapes::<false>(&[sector], &color);
+    }
+    pub fn draw_arc_aa(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if thickness == 0 {
+            return;
+        }
+        let inner_r = r.saturating_sub(thickness);
+        let sector = sector_shape(center_x, center_y, inner_r, r, start_angle, end_angle);
+        self.m_fill_shapes::<true>(&[sector], &color);
+    }
+    pub fn fill_pie(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_pie_paint(center_x, center_y, r, start_angle, end_angle, &color.into());
+    }
+    pub fn fill_pie_paint(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        paint: &impl Paint,
+    ) {
+        let sector = sector_shape(center_x, center_y, 0, r, start_angle, end_angle);
+        self.m_fill_shapes::<false>(&[sector], paint);
+    }
+    pub fn fill_pie_aa(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        color: impl Into<Color>,
+    ) {
+        self.fill_pie_aa_paint(center_x, center_y, r, start_angle, end_angle, &color.into());
+    }
+    pub fn fill_pie_aa_paint(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        r: u32,
+        start_angle: f32,
+        end_angle: f32,
+        paint: &impl Paint,
+    ) {
+        let sector = sector_shape(center_x, center_y, 0, r, start_angle, end_angle);
+        self.m_fill_shapes::<true>(&[sector], paint);
+    }
+    // radii are for the top left, top right, bottom right and bottom left corners
+    pub fn fill_rounded_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        color: impl Into<Color>,
+    ) {
+        self.fill_rounded_rect_paint(x, y, w, h, radii, &color.into());
+    }
+    pub fn fill_rounded_rect_paint(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        paint: &impl Paint,
+    ) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let rect = rounded_rect_shape(x, y, w, h, radii, 0);
+        self.m_fill_shapes::<false>(&[rect], paint);
+    }
+    pub fn fill_rounded_rect_aa(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        color: impl Into<Color>,
+    ) {
+        self.fill_rounded_rect_aa_paint(x, y, w, h, radii, &color.into());
+    }
+    pub fn fill_rounded_rect_aa_paint(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        paint: &impl Paint,
+    ) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let rect = rounded_rect_shape(x, y, w, h, radii, 0);
+        self.m_fill_shapes::<true>(&[rect], paint);
+    }
+    // like draw_rect, the outline grows inwards
+    pub fn draw_rounded_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if w == 0 || h == 0 || thickness == 0 {
+            return;
+        }
+        let rect = rounded_rect_shape(x, y, w, h, radii, thickness);
+        self.m_fill_shapes::<false>(&[rect], &color);
+    }
+    pub fn draw_rounded_rect_aa(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        radii: [u32; 4],
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if w == 0 || h == 0 || thickness == 0 {
+            return;
+        }
+        let rect = rounded_rect_shape(x, y, w, h, radii, thickness);
+        self.m_fill_shapes::<true>(&[rect], &color);
+    }
+    pub fn fill_rect(&mut self, x0: i32, y0: i32, w: i32, h: i32, color: impl Into<Color>) {
+        self.fill_rect_paint(x0, y0, w, h, &color.into());
+    }
+    pub fn fill_rect_paint(&mut self, x0: i32, y0: i32, w: i32, h: i32, paint: &impl Paint) {
         if let Some(((x0, y0), (x1, y1))) = normalize_rect(x0, y0, w, h, self.width, self.height) {
             (y0..=y1).for_each(|y| {
-                self.draw_horizontal_line_unchecked(x0, x1, y, color);
+                self.m_draw_span(x0, x1, y, paint);
             });
         }
     }
-    pub fn fill_text(&mut self, text: &str, x0: i32, y0: i32, glyph_size: usize, color: u32) {
+    // the outline grows inwards, so it covers the edge of fill_rect(x, y, w, h, ..)
+    pub fn draw_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into().to_u32();
+        if w == 0 || h == 0 || thickness == 0 {
+            return;
+        }
+        let x1 = if w > 0 { x + w - 1 } else { x + w + 1 };
+        let y1 = if h > 0 { y + h - 1 } else { y + h + 1 };
+        let (x0, x1) = (x.min(x1), x.max(x1));
+        let (y0, y1) = (y.min(y1), y.max(y1));
+        let w = x1 - x0 + 1;
+        let h = y1 - y0 + 1;
+        let t = thickness.min(i32::MAX as u32) as i32;
+        if 2 * t >= w || 2 * t >= h {
+            self.fill_rect(x0, y0, w, h, color);
+            return;
+        }
+        // the four sides do not overlap, so every pixel is drawn once
+        self.fill_rect(x0, y0, w, t, color);
+        self.fill_rect(x0, y1 - t + 1, w, t, color);
+        self.fill_rect(x0, y0 + t, t, h - 2 * t, color);
+        self.fill_rect(x1 - t + 1, y0 + t, t, h - 2 * t, color);
+    }
+    // the outline grows inwards from the edges like draw_rect, which lie on pixel boundaries,
+    // so every pixel is fully covered or not at all and there is nothing to smooth
+    pub fn draw_rect_aa(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        thickness: u32,
+        color: impl Into<Color>,
+    ) {
+        self.draw_rect(x, y, w, h, thickness, color);
+    }
+    pub fn fill_text(
+        &mut self,
+        text: &str,
+        x0: i32,
+        y0: i32,
+        glyph_size: usize,
+        color: impl Into<Color>,
+    ) {
+        self.fill_text_paint(text, x0, y0, glyph_size, &color.into());
+    }
+    pub fn fill_text_paint(
+        &mut self,
+        text: &str,
+        x0: i32,
+        y0: i32,
+        glyph_size: usize,
+        paint: &impl Paint,
+    ) {
         let glyph_size = glyph_size as i32;
         for (i, ref ch) in text.chars().enumerate() {
             let x = x0 + (i * (DEFAULT_FONT_WIDTH + DEFAULT_FONT_SPACING)) as i32 * glyph_size;
@@ -517,34 +1575,21 @@ impl<'b> Renderer<'b> {
             for (dy, row) in glyph.iter().enumerate() {
                 for (dx, &pixel) in row.iter().enumerate() {
                     if pixel == 1 {
-                        self.fill_rect(
+                        self.fill_rect_paint(
                             x + dx as i32 * glyph_size,
                             y0 + dy as i32 * glyph_size,
                             glyph_size,
                             glyph_size,
-                            color,
+                            paint,
                         )
                     }
                 }
             }
         }
     }
-    pub fn save_to_ppm_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
-        let file = File::create(path)?;
-        let mut file = BufWriter::new(file);
-        write!(file, "P6\n{} {} 255\n", self.width, self.height)?;
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let pixel = self.buffer[(y * self.width + x) as usize];
-                let rgb: [u8; 3] = [
-                    ((pixel) & 0xFF) as u8,
-                    ((pixel >> 8) & 0xFF) as u8,
-                    ((pixel >> (8 * 2)) & 0xFF) as u8,
-                ];
-                file.write_all(&rgb)?;
-            }
-        }
-        Ok(())
+    // the rows of the canvas, width pixels long, skipping what's outside of a sub canvas
+    pub fn rows(&self) -> impl Iterator<Item = &[F::Pixel]> + Clone + use<'_, 'b, F> {
+        (0..self.height).map(|y| self.get_row_unchecked(y))
     }
     #[inline]
     fn draw_pixel(&mut self, x: i32, y: i32, color: u32) {
@@ -563,20 +1608,32 @@ impl<'b> Renderer<'b> {
     }
     fn m_draw_pixel_unchecked<const BLENDING_ENABLED: bool>(&mut self, x: u32, y: u32, color: u32) {
         if BLENDING_ENABLED {
-            blend_color(&mut self.buffer[(y * self.stride + x) as usize], color);
+            F::blend(&mut self.buffer[(y * self.stride + x) as usize], color);
         } else {
-            self.buffer[(y * self.stride + x) as usize] = color;
+            self.buffer[(y * self.stride + x) as usize] = F::from_rgba(color);
         }
     }
     #[inline]
-    fn aa_color(&self, count: u8, color: u32) -> u32 {
-        (self.aa_color_fn)(count, color)
+    fn aa_color(&self, t: f32, color: u32) -> u32 {
+        (self.aa_color_fn)(t, color)
+    }
+    // t is the coverage of the pixel, in [0, 1]
+    fn draw_pixel_aa(&mut self, x: i32, y: i32, color: u32, t: f32) {
+        if x < 0 || y < 0 || t <= 0f32 {
+            return;
+        }
+        let x = x as u32;
+        let y = y as u32;
+        if x < self.width && y < self.height {
+            let color = self.aa_color(t.min(1f32), color);
+            self.m_draw_pixel_unchecked::<true>(x, y, color);
+        }
     }
     fn draw_pixel_unchecked_aa(
         &mut self,
         x: u32,
         y: u32,
-        color: u32,
+        paint: &impl Paint,
         condition: impl Fn(f32, f32) -> bool,
     ) {
         let mut count_aa = 0;
@@ -590,7 +1647,8 @@ impl<'b> Renderer<'b> {
             }
         }
         if count_aa > 0 {
-            let color = self.aa_color(count_aa, color);
+            let t = count_aa as f32 / (AA_RES * AA_RES) as f32;
+            let color = self.aa_color(t, paint.color_at(x as f32, y as f32));
             self.m_draw_pixel_unchecked::<true>(x, y, color);
         }
     }
@@ -615,11 +1673,75 @@ impl<'b> Renderer<'b> {
             }
         }
         if let Some(color) = color {
-            let color = self.aa_color(count_aa, color);
+            let t = count_aa as f32 / (AA_RES * AA_RES) as f32;
+            let color = self.aa_color(t, color);
             self.m_draw_pixel_unchecked::<true>(x, y, color);
         }
     }
 }
+// writing to files and streams needs std
+#[cfg(feature = "std")]
+impl<F: PixelFormat> Renderer<'_, F> {
+    // images are written as 0xAABBGGRR, whatever the pixel format,
+    // other formats are converted into a copy of the canvas first
+    pub fn write_image(&self, writer: impl Write, format: ImageFormat) -> std::io::Result<()> {
+        if F::as_rgba(&[]).is_some() {
+            let rows = self.rows().map(|row| F::as_rgba(row).unwrap());
+            return image::write_rows(writer, self.width, self.height, rows, format);
+        }
+        let width = self.width as usize;
+        let pixels: Vec<u32> = self.rows().flatten().map(|&p| F::to_rgba(p)).collect();
+        let rows = (0..self.height as usize).map(|y| &pixels[y * width..(y + 1) * width]);
+        image::write_rows(writer, self.width, self.height, rows, format)
+    }
+    pub fn save_to_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        format: ImageFormat,
+    ) -> std::io::Result<()> {
+        self.write_image(BufWriter::new(File::create(path)?), format)
+    }
+    pub fn save_to_ppm_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Ppm)
+    }
+    pub fn write_ppm(&self, writer: impl Write) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Ppm)
+    }
+    pub fn save_to_png_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Png)
+    }
+    pub fn write_png(&self, writer: impl Write) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Png)
+    }
+    pub fn save_to_bmp_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Bmp)
+    }
+    pub fn write_bmp(&self, writer: impl Write) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Bmp)
+    }
+    pub fn save_to_tga_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        rle: bool,
+    ) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Tga { rle })
+    }
+    pub fn write_tga(&self, writer: impl Write, rle: bool) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Tga { rle })
+    }
+    pub fn save_to_qoi_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Qoi)
+    }
+    pub fn write_qoi(&self, writer: impl Write) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Qoi)
+    }
+    pub fn save_to_farbfeld_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.save_to_file(path, ImageFormat::Farbfeld)
+    }
+    pub fn write_farbfeld(&self, writer: impl Write) -> std::io::Result<()> {
+        self.write_image(writer, ImageFormat::Farbfeld)
+    }
+}
 
 const DEFAULT_FONT_WIDTH: usize = 5;
 const DEFAULT_FONT_HEIGHT: usize = 8;
@@ -1321,6 +2443,952 @@ static DEFAULT_FONT_GLYPHS: phf::Map<char, Glyph> = phf_map! {
     ],
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Square,
+    Round,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round,
+}
+
+// where the fill_*_paint functions take the color of every pixel from,
+// implemented for solid colors (u32), Gradient, Pattern and closures Fn(x, y) -> color
+pub trait Paint {
+    // (x, y) is the center of the pixel
+    fn color_at(&self, x: f32, y: f32) -> u32;
+    // Some when every pixel gets the same color, so whole spans can be filled at once
+    fn solid_color(&self) -> Option<u32> {
+        None
+    }
+}
+impl Paint for u32 {
+    #[inline]
+    fn color_at(&self, _x: f32, _y: f32) -> u32 {
+        *self
+    }
+    #[inline]
+    fn solid_color(&self) -> Option<u32> {
+        Some(*self)
+    }
+}
+
+impl Paint for Color {
+    #[inline]
+    fn color_at(&self, _x: f32, _y: f32) -> u32 {
+        self.to_u32()
+    }
+    #[inline]
+    fn solid_color(&self) -> Option<u32> {
+        Some(self.to_u32())
+    }
+}
+impl<F: Fn(f32, f32) -> u32> Paint for F {
+    #[inline]
+    fn color_at(&self, x: f32, y: f32) -> u32 {
+        self(x, y)
+    }
+}
+
+// an image repeated over the whole canvas, with one of its copies at (x, y)
+#[derive(Clone, Copy, Debug)]
+pub struct Pattern<'a> {
+    pixels: &'a [u32],
+    width: u32,
+    height: u32,
+    stride: u32,
+    pub x: i32,
+    pub y: i32,
+}
+impl<'a> Pattern<'a> {
+    pub fn new(pixels: &'a [u32], width: u32, height: u32) -> Self {
+        Self::with_stride(pixels, width, height, width)
+    }
+    pub fn with_stride(pixels: &'a [u32], width: u32, height: u32, stride: u32) -> Self {
+        assert!(width <= stride);
+        assert!(height == 0 || pixels.len() >= ((height - 1) * stride + width) as usize);
+        Self {
+            pixels,
+            width,
+            height,
+            stride,
+            x: 0,
+            y: 0,
+        }
+    }
+    pub fn from_renderer(renderer: &'a Renderer) -> Self {
+        Self::with_stride(
+            renderer.get_buffer(),
+            renderer.width,
+            renderer.height,
+            renderer.stride,
+        )
+    }
+    pub fn offset(mut self, x: i32, y: i32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+}
+impl Paint for Pattern<'_> {
+    #[inline]
+    fn color_at(&self, x: f32, y: f32) -> u32 {
+        if self.width == 0 || self.height == 0 {
+            return 0;
+        }
+        let x = (x.floor() as i32 - self.x).rem_euclid(self.width as i32) as u32;
+        let y = (y.floor() as i32 - self.y).rem_euclid(self.height as i32) as u32;
+        self.pixels[(y * self.stride + x) as usize]
+    }
+}
+
+// what happens outside of the [0, 1] range of the gradient
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpreadMode {
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+// in pixel coordinates, (x, y) being the center of pixel (x, y)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear { x0: f32, y0: f32, x1: f32, y1: f32 },
+    Radial { x: f32, y: f32, r: f32 },
+    // sweeps clockwise on the screen starting from angle
+    Conic { x: f32, y: f32, angle: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub spread: SpreadMode,
+    // sorted by offset
+    stops: Vec<(f32, u32)>,
+}
+impl Gradient {
+    pub fn new(kind: GradientKind) -> Self {
+        Self {
+            kind,
+            spread: SpreadMode::Pad,
+            stops: Vec::new(),
+        }
+    }
+    pub fn linear(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        Self::new(GradientKind::Linear { x0, y0, x1, y1 })
+    }
+    pub fn radial(x: f32, y: f32, r: f32) -> Self {
+        Self::new(GradientKind::Radial { x, y, r })
+    }
+    pub fn conic(x: f32, y: f32, angle: f32) -> Self {
+        Self::new(GradientKind::Conic { x, y, angle })
+    }
+    // stops with the same offset keep the order they were added in, for hard color changes
+    pub fn add_stop(&mut self, offset: f32, color: impl Into<Color>) -> &mut Self {
+        let color = color.into().to_u32();
+        let i = self.stops.partition_point(|&(o, _)| o <= offset);
+        self.stops.insert(i, (offset, color));
+        self
+    }
+    pub fn set_spread(&mut self, spread: SpreadMode) -> &mut Self {
+        self.spread = spread;
+        self
+    }
+    pub fn stops(&self) -> &[(f32, u32)] {
+        &self.stops
+    }
+    pub fn color_at(&self, x: f32, y: f32) -> u32 {
+        let t = match self.kind {
+            GradientKind::Linear { x0, y0, x1, y1 } => {
+                let dx = x1 - x0;
+                let dy = y1 - y0;
+                let len2 = dx * dx + dy * dy;
+                if len2 == 0f32 {
+                    0f32
+                } else {
+                    ((x - x0) * dx + (y - y0) * dy) / len2
+                }
+            }
+            GradientKind::Radial { x: cx, y: cy, r } => {
+                let dx = x - cx;
+                let dy = y - cy;
+                if r == 0f32 {
+                    1f32
+                } else {
+                    (dx * dx + dy * dy).sqrt() / r
+                }
+            }
+            GradientKind::Conic {
+                x: cx,
+                y: cy,
+                angle,
+            } => {
+                use core::f32::consts::TAU;
+                ((y - cy).atan2(x - cx) - angle).rem_euclid(TAU) / TAU
+            }
+        };
+        let t = match self.spread {
+            SpreadMode::Pad => t.clamp(0f32, 1f32),
+            SpreadMode::Repeat => fract(t),
+            SpreadMode::Reflect => {
+                let t = t.rem_euclid(2f32);
+                if t > 1f32 {
+                    2f32 - t
+                } else {
+                    t
+                }
+            }
+        };
+        self.color_at_offset(t)
+    }
+    fn color_at_offset(&self, t: f32) -> u32 {
+        let i = self.stops.partition_point(|&(o, _)| o <= t);
+        match (i.checked_sub(1).map(|i| self.stops[i]), self.stops.get(i)) {
+            (None, None) => 0,
+            (Some((_, color)), None) | (None, Some(&(_, color))) => color,
+            (Some((o0, c0)), Some(&(o1, c1))) => mix_color(c0, c1, (t - o0) / (o1 - o0)),
+        }
+    }
+}
+impl Paint for Gradient {
+    #[inline]
+    fn color_at(&self, x: f32, y: f32) -> u32 {
+        Gradient::color_at(self, x, y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    EvenOdd,
+    NonZero,
+}
+
+// always y0 < y1, winding remembers the original direction
+#[derive(Clone, Copy)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+// every contour is implicitly closed,
+// points are in pixel coordinates, (x, y) being the center of pixel (x, y)
+fn polygon_edges(contours: &[&[(f32, f32)]]) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for contour in contours {
+        let n = contour.len();
+        if n < 3 {
+            continue;
+        }
+        for i in 0..n {
+            let (x0, y0) = contour[i];
+            let (x1, y1) = contour[(i + 1) % n];
+            if y0 == y1 {
+                // horizontal edges never cross a scanline
+                continue;
+            }
+            let (x0, y0, x1, y1) = (x0 + 0.5, y0 + 0.5, x1 + 0.5, y1 + 0.5);
+            edges.push(if y0 < y1 {
+                Edge {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    winding: 1,
+                }
+            } else {
+                Edge {
+                    x0: x1,
+                    y0: y1,
+                    x1: x0,
+                    y1: y0,
+                    winding: -1,
+                }
+            });
+        }
+    }
+    edges
+}
+
+// spans are the [x0, x1) ranges inside the polygon on the horizontal line y
+fn scanline_spans(
+    edges: &[Edge],
+    y: f32,
+    rule: FillRule,
+    crossings: &mut Vec<(f32, i32)>,
+    spans: &mut Vec<(f32, f32)>,
+) {
+    crossings.clear();
+    spans.clear();
+    for edge in edges {
+        if edge.y0 <= y && y < edge.y1 {
+            let x = edge.x0 + (y - edge.y0) * (edge.x1 - edge.x0) / (edge.y1 - edge.y0);
+            crossings.push((x, edge.winding));
+        }
+    }
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut winding = 0;
+    for i in 0..crossings.len() {
+        let (x, w) = crossings[i];
+        winding += w;
+        let inside = match rule {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        };
+        if inside {
+            if let Some(&(next_x, _)) = crossings.get(i + 1) {
+                match spans.last_mut() {
+                    Some(span) if span.1 >= x => span.1 = next_x,
+                    _ => spans.push((x, next_x)),
+                }
+            }
+        }
+    }
+}
+
+// a miter longer than MITER_LIMIT * width / 2 falls back to a bevel
+const MITER_LIMIT: f32 = 4.0;
+
+// in continuous coordinates, the center of pixel (x, y) is (x + 0.5, y + 0.5)
+#[derive(Clone, Copy)]
+enum Shape {
+    Triangle {
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        r: f32,
+    },
+    // rotated by the angle whose cos and sin are given
+    Ellipse {
+        x: f32,
+        y: f32,
+        rx: f32,
+        ry: f32,
+        cos: f32,
+        sin: f32,
+    },
+    // radii are for the top left, top right, bottom right and bottom left corners,
+    // a positive thickness leaves only the border of that thickness
+    RoundedRect {
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        radii: [f32; 4],
+        thickness: f32,
+    },
+    // the part of the ring inner_r < d <= r between the angles start and start + sweep
+    Sector {
+        x: f32,
+        y: f32,
+        inner_r: f32,
+        r: f32,
+        start: f32,
+        sweep: f32,
+    },
+}
+impl Shape {
+    #[inline]
+    fn contains(&self, x: f32, y: f32) -> bool {
+        match *self {
+            Shape::Triangle {
+                x0,
+                y0,
+                x1,
+                y1,
+                x2,
+                y2,
+            } => xy_in_triangle(x, y, x0, y0, x1, y1, x2, y2),
+            Shape::Circle {
+                x: center_x,
+                y: center_y,
+                r,
+            } => {
+                let dx = x - center_x;
+                let dy = y - center_y;
+                dx * dx + dy * dy <= r * r
+            }
+            Shape::Ellipse {
+                x: center_x,
+                y: center_y,
+                rx,
+                ry,
+                cos,
+                sin,
+            } => {
+                let dx = x - center_x;
+                let dy = y - center_y;
+                let u = (dx * cos + dy * sin) / rx;
+                let v = (dy * cos - dx * sin) / ry;
+                u * u + v * v <= 1f32
+            }
+            Shape::Sector {
+                x: center_x,
+                y: center_y,
+                inner_r,
+                r,
+                start,
+                sweep,
+            } => {
+                let dx = x - center_x;
+                let dy = y - center_y;
+                let d = dx * dx + dy * dy;
+                d <= r * r
+                    && (inner_r <= 0f32 || inner_r * inner_r < d)
+                    && angle_in_range(dy.atan2(dx), start, sweep)
+            }
+            Shape::RoundedRect {
+                x0,
+                y0,
+                x1,
+                y1,
+                radii,
+                thickness,
+            } => {
+                xy_in_rounded_rect(x, y, x0, y0, x1, y1, radii)
+                    && (thickness <= 0f32
+                        || !xy_in_rounded_rect(
+                            x,
+                            y,
+                            x0 + thickness,
+                            y0 + thickness,
+                            x1 - thickness,
+                            y1 - thickness,
+                            radii.map(|r| (r - thickness).max(0f32)),
+                        ))
+            }
+        }
+    }
+    // return (x_min, y_min, x_max, y_max)
+    fn bounding_box(&self) -> (f32, f32, f32, f32) {
+        match *self {
+            Shape::Triangle {
+                x0,
+                y0,
+                x1,
+                y1,
+                x2,
+                y2,
+            } => (
+                x0.min(x1).min(x2),
+                y0.min(y1).min(y2),
+                x0.max(x1).max(x2),
+                y0.max(y1).max(y2),
+            ),
+            Shape::Circle { x, y, r } | Shape::Sector { x, y, r, .. } => {
+                (x - r, y - r, x + r, y + r)
+            }
+            Shape::RoundedRect { x0, y0, x1, y1, .. } => (x0, y0, x1, y1),
+            Shape::Ellipse {
+                x,
+                y,
+                rx,
+                ry,
+                cos,
+                sin,
+            } => {
+                let hx = (rx * rx * cos * cos + ry * ry * sin * sin).sqrt();
+                let hy = (rx * rx * sin * sin + ry * ry * cos * cos).sqrt();
+                (x - hx, y - hy, x + hx, y + hy)
+            }
+        }
+    }
+}
+
+fn rounded_rect_shape(x: i32, y: i32, w: i32, h: i32, radii: [u32; 4], thickness: u32) -> Shape {
+    // same corners as fill_rect(x, y, w, h, ..)
+    let x1 = if w > 0 { x + w } else { x + w + 1 };
+    let y1 = if h > 0 { y + h } else { y + h + 1 };
+    let x0 = if w > 0 { x } else { x + 1 };
+    let y0 = if h > 0 { y } else { y + 1 };
+    let (x0, x1) = (x0.min(x1) as f32, x0.max(x1) as f32);
+    let (y0, y1) = (y0.min(y1) as f32, y0.max(y1) as f32);
+    let w = x1 - x0;
+    let h = y1 - y0;
+    // like css, scale all the radii down when the corners of a side overlap
+    let [top_left, top_right, bottom_right, bottom_left] = radii.map(|r| r as f32);
+    let scale = [
+        w / (top_left + top_right),
+        w / (bottom_left + bottom_right),
+        h / (top_left + bottom_left),
+        h / (top_right + bottom_right),
+    ]
+    .into_iter()
+    .fold(1f32, f32::min);
+    Shape::RoundedRect {
+        x0,
+        y0,
+        x1,
+        y1,
+        radii: radii.map(|r| r as f32 * scale),
+        thickness: thickness as f32,
+    }
+}
+
+fn ellipse_shape(center_x: i32, center_y: i32, rx: u32, ry: u32, rotation: f32) -> Shape {
+    Shape::Ellipse {
+        x: center_x as f32 + 0.5,
+        y: center_y as f32 + 0.5,
+        rx: rx as f32,
+        ry: ry as f32,
+        cos: rotation.cos(),
+        sin: rotation.sin(),
+    }
+}
+
+fn sector_shape(
+    center_x: i32,
+    center_y: i32,
+    inner_r: u32,
+    r: u32,
+    start_angle: f32,
+    end_angle: f32,
+) -> Shape {
+    let (start, sweep) = normalize_angles(start_angle, end_angle);
+    Shape::Sector {
+        x: center_x as f32 + 0.5,
+        y: center_y as f32 + 0.5,
+        inner_r: inner_r as f32,
+        r: r as f32,
+        start,
+        sweep,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    Arc {
+        x: f32,
+        y: f32,
+        r: f32,
+        start_angle: f32,
+        end_angle: f32,
+    },
+    Close,
+}
+
+// points are in pixel coordinates, (x, y) being the center of pixel (x, y)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::MoveTo(x, y));
+        self
+    }
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::LineTo(x, y));
+        self
+    }
+    pub fn quad_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> &mut Self {
+        self.commands.push(PathCommand::QuadTo(x1, y1, x2, y2));
+        self
+    }
+    pub fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32) -> &mut Self {
+        self.commands
+            .push(PathCommand::CubicTo(x1, y1, x2, y2, x3, y3));
+        self
+    }
+    // like the canvas arc, a line connects the current point to the start of the arc,
+    // the arc goes clockwise on the screen when end_angle > start_angle
+    pub fn arc(&mut self, x: f32, y: f32, r: f32, start_angle: f32, end_angle: f32) -> &mut Self {
+        self.commands.push(PathCommand::Arc {
+            x,
+            y,
+            r,
+            start_angle,
+            end_angle,
+        });
+        self
+    }
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+    // return the subpaths as polylines, and whether they are closed
+    fn flatten(&self, tolerance: f32) -> Vec<(Vec<(f32, f32)>, bool)> {
+        let mut contours = Vec::new();
+        let mut points: Vec<(f32, f32)> = Vec::new();
+        // where a subpath starts after close
+        let mut last_start = None;
+        let mut finish = |points: &mut Vec<(f32, f32)>, closed: bool| {
+            if points.len() > 1 {
+                contours.push((core::mem::take(points), closed));
+            } else {
+                points.clear();
+            }
+        };
+        for &command in &self.commands {
+            if points.is_empty() && command != PathCommand::Close {
+                if let Some(start) = last_start.take() {
+                    points.push(start);
+                }
+            }
+            match command {
+                PathCommand::MoveTo(x, y) => {
+                    finish(&mut points, false);
+                    points.push((x, y));
+                }
+                // without a current point, line_to behaves like move_to
+                PathCommand::LineTo(x, y) => points.push((x, y)),
+                PathCommand::QuadTo(x1, y1, x2, y2) => {
+                    if points.is_empty() {
+                        points.push((x1, y1));
+                    }
+                    let (x0, y0) = points[points.len() - 1];
+                    flatten_quad_bezier(&mut points, x0, y0, x1, y1, x2, y2, tolerance);
+                }
+                PathCommand::CubicTo(x1, y1, x2, y2, x3, y3) => {
+                    if points.is_empty() {
+                        points.push((x1, y1));
+                    }
+                    let (x0, y0) = points[points.len() - 1];
+                    flatten_cubic_bezier(&mut points, x0, y0, x1, y1, x2, y2, x3, y3, tolerance);
+                }
+                PathCommand::Arc {
+                    x,
+                    y,
+                    r,
+                    start_angle,
+                    end_angle,
+                } => {
+                    use core::f32::consts::TAU;
+                    let sweep = (end_angle - start_angle).clamp(-TAU, TAU);
+                    let r = r.abs();
+                    // the largest angle step whose chord stays within tolerance of the arc,
+                    // 2 acos(1 - tolerance / r) written with asin, since 1 - tolerance / r
+                    // rounds to 1 for huge radii
+                    let step = if r > tolerance {
+                        4f32 * (tolerance / (2f32 * r)).sqrt().asin()
+                    } else {
+                        TAU / 4f32
+                    };
+                    let n = ((sweep.abs() / step.max(MIN_ARC_STEP)).ceil() as usize)
+                        .clamp(1, MAX_ARC_SEGMENTS);
+                    for i in 0..=n {
+                        let angle = start_angle + sweep * i as f32 / n as f32;
+                        points.push((x + r * angle.cos(), y + r * angle.sin()));
+                    }
+                }
+                PathCommand::Close => {
+                    if let Some(&start) = points.first() {
+                        last_start = Some(start);
+                    }
+                    finish(&mut points, true);
+                }
+            }
+        }
+        finish(&mut points, false);
+        contours
+    }
+    fn stroke(&self, width: f32, cap: LineCap, join: LineJoin) -> Vec<Shape> {
+        self.flatten(FLATTEN_TOLERANCE)
+            .iter()
+            .flat_map(|(points, closed)| stroke_polyline(points, *closed, width, cap, join))
+            .collect()
+    }
+}
+
+// the maximum distance in pixels between a curve and its flattened polyline
+pub const FLATTEN_TOLERANCE: f32 = 0.25;
+const FLATTEN_MAX_DEPTH: u32 = 16;
+// arcs with huge radii would need millions of segments to stay within the tolerance
+const MIN_ARC_STEP: f32 = 1e-3;
+const MAX_ARC_SEGMENTS: usize = 1 << 12;
+
+// append the points of the flattened curve to points, except the starting point (x0, y0),
+// so consecutive curves can be flattened into the same polyline or polygon,
+// panics unless tolerance is positive and finite
+pub fn flatten_quad_bezier(
+    points: &mut Vec<(f32, f32)>,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    tolerance: f32,
+) {
+    assert!(tolerance > 0f32 && tolerance.is_finite());
+    fn subdivide(
+        points: &mut Vec<(f32, f32)>,
+        (x0, y0): (f32, f32),
+        (x1, y1): (f32, f32),
+        (x2, y2): (f32, f32),
+        tolerance: f32,
+        depth: u32,
+    ) {
+        // the curve is at most |p0 - 2p1 + p2| / 4 away from its chord
+        let dx = x0 - 2f32 * x1 + x2;
+        let dy = y0 - 2f32 * y1 + y2;
+        if dx * dx + dy * dy <= 16f32 * tolerance * tolerance || depth >= FLATTEN_MAX_DEPTH {
+            points.push((x2, y2));
+            return;
+        }
+        // de Casteljau
+        let (x01, y01) = ((x0 + x1) / 2f32, (y0 + y1) / 2f32);
+        let (x12, y12) = ((x1 + x2) / 2f32, (y1 + y2) / 2f32);
+        let (x012, y012) = ((x01 + x12) / 2f32, (y01 + y12) / 2f32);
+        subdivide(
+            points,
+            (x0, y0),
+            (x01, y01),
+            (x012, y012),
+            tolerance,
+            depth + 1,
+        );
+        subdivide(
+            points,
+            (x012, y012),
+            (x12, y12),
+            (x2, y2),
+            tolerance,
+            depth + 1,
+        );
+    }
+    subdivide(points, (x0, y0), (x1, y1), (x2, y2), tolerance, 0);
+}
+
+pub fn flatten_cubic_bezier(
+    points: &mut Vec<(f32, f32)>,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    x3: f32,
+    y3: f32,
+    tolerance: f32,
+) {
+    assert!(tolerance > 0f32 && tolerance.is_finite());
+    fn subdivide(
+        points: &mut Vec<(f32, f32)>,
+        (x0, y0): (f32, f32),
+        (x1, y1): (f32, f32),
+        (x2, y2): (f32, f32),
+        (x3, y3): (f32, f32),
+        tolerance: f32,
+        depth: u32,
+    ) {
+        // Roger Willcocks' flatness criterion
+        let ux = 3f32 * x1 - 2f32 * x0 - x3;
+        let uy = 3f32 * y1 - 2f32 * y0 - y3;
+        let vx = 3f32 * x2 - x0 - 2f32 * x3;
+        let vy = 3f32 * y2 - y0 - 2f32 * y3;
+        let flatness = (ux * ux).max(vx * vx) + (uy * uy).max(vy * vy);
+        if flatness <= 16f32 * tolerance * tolerance || depth >= FLATTEN_MAX_DEPTH {
+            points.push((x3, y3));
+            return;
+        }
+        // de Casteljau
+        let (x01, y01) = ((x0 + x1) / 2f32, (y0 + y1) / 2f32);
+        let (x12, y12) = ((x1 + x2) / 2f32, (y1 + y2) / 2f32);
+        let (x23, y23) = ((x2 + x3) / 2f32, (y2 + y3) / 2f32);
+        let (x012, y012) = ((x01 + x12) / 2f32, (y01 + y12) / 2f32);
+        let (x123, y123) = ((x12 + x23) / 2f32, (y12 + y23) / 2f32);
+        let (x0123, y0123) = ((x012 + x123) / 2f32, (y012 + y123) / 2f32);
+        subdivide(
+            points,
+            (x0, y0),
+            (x01, y01),
+            (x012, y012),
+            (x0123, y0123),
+            tolerance,
+            depth + 1,
+        );
+        subdivide(
+            points,
+            (x0123, y0123),
+            (x123, y123),
+            (x23, y23),
+            (x3, y3),
+            tolerance,
+            depth + 1,
+        );
+    }
+    subdivide(points, (x0, y0), (x1, y1), (x2, y2), (x3, y3), tolerance, 0);
+}
+
+// angles are in radians, clockwise on the screen starting from the positive x axis
+fn angle_in_range(angle: f32, start: f32, sweep: f32) -> bool {
+    use core::f32::consts::TAU;
+    sweep >= TAU || (angle - start).rem_euclid(TAU) <= sweep
+}
+
+// return (start, sweep), sweep being in [0, TAU]
+fn normalize_angles(start_angle: f32, end_angle: f32) -> (f32, f32) {
+    if end_angle < start_angle {
+        (end_angle, start_angle - end_angle)
+    } else {
+        (start_angle, end_angle - start_angle)
+    }
+}
+
+// points are in pixel coordinates, (x, y) being the center of pixel (x, y)
+fn stroke_polyline(
+    points: &[(f32, f32)],
+    closed: bool,
+    width: f32,
+    cap: LineCap,
+    join: LineJoin,
+) -> Vec<Shape> {
+    let mut shapes = Vec::new();
+    let hw = width / 2f32;
+    if hw <= 0f32 {
+        return shapes;
+    }
+    let mut points: Vec<(f32, f32)> = points.iter().map(|&(x, y)| (x + 0.5, y + 0.5)).collect();
+    points.dedup();
+    if closed && points.len() > 2 && points.first() == points.last() {
+        points.pop();
+    }
+    let closed = closed && points.len() > 2;
+    let triangle =
+        |(x0, y0): (f32, f32), (x1, y1): (f32, f32), (x2, y2): (f32, f32)| Shape::Triangle {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+        };
+
+    match points[..] {
+        [] => return shapes,
+        [(x, y)] => {
+            match cap {
+                LineCap::Butt => {}
+                LineCap::Square => {
+                    shapes.push(triangle(
+                        (x - hw, y - hw),
+                        (x + hw, y - hw),
+                        (x + hw, y + hw),
+                    ));
+                    shapes.push(triangle(
+                        (x - hw, y - hw),
+                        (x + hw, y + hw),
+                        (x - hw, y + hw),
+                    ));
+                }
+                LineCap::Round => shapes.push(Shape::Circle { x, y, r: hw }),
+            }
+            return shapes;
+        }
+        _ => {}
+    }
+
+    let n = points.len();
+    let segment_count = if closed { n } else { n - 1 };
+    // unit direction and unit normal of every segment
+    let segments: Vec<((f32, f32), (f32, f32))> = (0..segment_count)
+        .map(|i| {
+            let (x0, y0) = points[i];
+            let (x1, y1) = points[(i + 1) % n];
+            let len = ((x1 - x0) * (x1 - x0) + (y1 - y0) * (y1 - y0)).sqrt();
+            let (dx, dy) = ((x1 - x0) / len, (y1 - y0) / len);
+            ((dx, dy), (-dy, dx))
+        })
+        .collect();
+
+    // segment bodies
+    for (i, &((dx, dy), (nx, ny))) in segments.iter().enumerate() {
+        let (mut x0, mut y0) = points[i];
+        let (mut x1, mut y1) = points[(i + 1) % n];
+        if !closed && cap == LineCap::Square {
+            if i == 0 {
+                x0 -= dx * hw;
+                y0 -= dy * hw;
+            }
+            if i == segment_count - 1 {
+                x1 += dx * hw;
+                y1 += dy * hw;
+            }
+        }
+        let a = (x0 + nx * hw, y0 + ny * hw);
+        let b = (x1 + nx * hw, y1 + ny * hw);
+        let c = (x1 - nx * hw, y1 - ny * hw);
+        let d = (x0 - nx * hw, y0 - ny * hw);
+        shapes.push(triangle(a, b, c));
+        shapes.push(triangle(a, c, d));
+    }
+
+    // caps
+    if !closed && cap == LineCap::Round {
+        let (x, y) = points[0];
+        shapes.push(Shape::Circle { x, y, r: hw });
+        let (x, y) = points[n - 1];
+        shapes.push(Shape::Circle { x, y, r: hw });
+    }
+
+    // joins
+    let joints = if closed { 0..n } else { 1..n - 1 };
+    for i in joints {
+        let (x, y) = points[i];
+        let ((dx_in, dy_in), (nx_in, ny_in)) = segments[(i + segment_count - 1) % segment_count];
+        let ((dx_out, dy_out), (nx_out, ny_out)) = segments[i];
+        let cross = dx_in * dy_out - dy_in * dx_out;
+        let dot = dx_in * dx_out + dy_in * dy_out;
+        if cross.abs() < f32::EPSILON && dot > 0f32 {
+            // straight, the segment bodies already meet
+            continue;
+        }
+        if join == LineJoin::Round {
+            shapes.push(Shape::Circle { x, y, r: hw });
+            continue;
+        }
+        // the outer side of the turn
+        let side = if cross > 0f32 { -hw } else { hw };
+        let outer_in = (x + nx_in * side, y + ny_in * side);
+        let outer_out = (x + nx_out * side, y + ny_out * side);
+        shapes.push(triangle((x, y), outer_in, outer_out));
+        if join == LineJoin::Miter {
+            let (mx, my) = (nx_in + nx_out, ny_in + ny_out);
+            let len = (mx * mx + my * my).sqrt();
+            if len < f32::EPSILON {
+                continue;
+            }
+            // cos of the half angle between the two segment normals
+            let cos_half = (mx * nx_in + my * ny_in) / len;
+            if cos_half * MITER_LIMIT <= 1f32 {
+                continue;
+            }
+            let miter = side / cos_half / len;
+            let tip = (x + mx * miter, y + my * miter);
+            shapes.push(triangle(outer_in, tip, outer_out));
+        }
+    }
+    shapes
+}
+
 #[derive(Clone)]
 struct Line2D {
     x0: f32,
@@ -1512,18 +3580,69 @@ impl Ray {
     }
 }
 
+// bresenham
+// Taylor Expansion to get rid of sqrt
+// and other approximation:
+// (x, y) is inside when 2x^2 + y^2 + (y - 1)^2 < 2r^2, the decision of the midpoint circle.
+// yields the half width of the rows center_y + dy and center_y - dy, for dy from 0 to r
+struct CircleRows {
+    r: i64,
+    dy: i64,
+    half_width: i64,
+    // below the diagonal the half width is the x of the arc instead of its y
+    past_diagonal: bool,
+}
+impl CircleRows {
+    fn new(r: i32) -> Self {
+        Self {
+            r: r.max(0) as i64,
+            dy: 0,
+            half_width: r.max(0) as i64,
+            past_diagonal: false,
+        }
+    }
+    fn inside(&self, x: i64, y: i64) -> bool {
+        2 * x * x + y * y + (y - 1) * (y - 1) < 2 * self.r * self.r
+    }
+}
+impl Iterator for CircleRows {
+    type Item = i32;
+    fn next(&mut self) -> Option<i32> {
+        if self.dy > self.r {
+            return None;
+        }
+        // both walks only ever move the half width inwards
+        if !self.past_diagonal {
+            while self.half_width > 0 && !self.inside(self.dy, self.half_width) {
+                self.half_width -= 1;
+            }
+            if self.half_width < self.dy {
+                self.past_diagonal = true;
+                self.half_width = self.dy;
+            }
+        }
+        if self.past_diagonal {
+            while self.half_width > 0 && !self.inside(self.half_width, self.dy) {
+                self.half_width -= 1;
+            }
+        }
+        self.dy += 1;
+        Some(self.half_width as i32)
+    }
+}
+
 fn sort_by_y(x0: &mut i32, y0: &mut i32, x1: &mut i32, y1: &mut i32, x2: &mut i32, y2: &mut i32) {
     if y0 > y1 {
-        std::mem::swap(y0, y1);
-        std::mem::swap(x0, x1);
+        core::mem::swap(y0, y1);
+        core::mem::swap(x0, x1);
     }
     if y1 > y2 {
-        std::mem::swap(y1, y2);
-        std::mem::swap(x1, x2);
+        core::mem::swap(y1, y2);
+        core::mem::swap(x1, x2);
     }
     if y0 > y1 {
-        std::mem::swap(y0, y1);
-        std::mem::swap(x0, x1);
+        core::mem::swap(y0, y1);
+        core::mem::swap(x0, x1);
     }
 }
 
@@ -1539,65 +3658,40 @@ fn sort_by_y3(
     c2: &mut u32,
 ) {
     if y0 > y1 {
-        std::mem::swap(y0, y1);
-        std::mem::swap(x0, x1);
-        std::mem::swap(c0, c1);
+        core::mem::swap(y0, y1);
+        core::mem::swap(x0, x1);
+        core::mem::swap(c0, c1);
     }
     if y1 > y2 {
-        std::mem::swap(y1, y2);
-        std::mem::swap(x1, x2);
-        std::mem::swap(c1, c2);
+        core::mem::swap(y1, y2);
+        core::mem::swap(x1, x2);
+        core::mem::swap(c1, c2);
     }
     if y0 > y1 {
-        std::mem::swap(y0, y1);
-        std::mem::swap(x0, x1);
-        std::mem::swap(c0, c1);
-    }
-}
-
-fn blend_color(bottom_color: &mut u32, top_color: u32) {
-    let mut bottom_color_components = [0u8; 4];
-    for (i, color_component) in bottom_color_components.iter_mut().enumerate() {
-        *color_component = (*bottom_color >> (8 * i) & 0xff) as u8;
-    }
-    let mut top_color_components = [0u8; 4];
-    for (i, color_component) in top_color_components.iter_mut().enumerate() {
-        *color_component = (top_color >> (8 * i) & 0xff) as u8;
-    }
-    let aa = top_color_components[3] as u16;
-    bottom_color_components
-        .iter_mut()
-        .zip(top_color_components.iter())
-        .take(3)
-        .for_each(|(bottom_color_component, &color_component)| {
-            *bottom_color_component = (((color_component as u16) * aa
-                + (0xff - aa) * (*bottom_color_component as u16))
-                / 0xff) as u8;
-        });
-    *bottom_color &= 0xff000000;
-    for (i, bottom_color_component) in bottom_color_components.iter().enumerate().take(3) {
-        *bottom_color |= (*bottom_color_component as u32) << (8 * i);
+        core::mem::swap(y0, y1);
+        core::mem::swap(x0, x1);
+        core::mem::swap(c0, c1);
     }
 }
 
 #[inline]
-fn red_part(color: u32) -> u32 {
+pub(crate) fn red_part(color: u32) -> u32 {
     color & 0xff
 }
 #[inline]
-fn green_part(color: u32) -> u32 {
+pub(crate) fn green_part(color: u32) -> u32 {
     (color >> 8) & 0xff
 }
 #[inline]
-fn blue_part(color: u32) -> u32 {
+pub(crate) fn blue_part(color: u32) -> u32 {
     (color >> (8 * 2)) & 0xff
 }
 #[inline]
-fn alpha_part(color: u32) -> u32 {
+pub(crate) fn alpha_part(color: u32) -> u32 {
     (color >> (8 * 3)) & 0xff
 }
 #[inline]
-fn rgba2color(r: u32, g: u32, b: u32, a: u32) -> u32 {
+pub(crate) fn rgba2color(r: u32, g: u32, b: u32, a: u32) -> u32 {
     let mut color = 0u32;
     color |= r & 0x000000ff;
     color |= (g << 8) & 0x0000ff00;
@@ -1606,6 +3700,16 @@ fn rgba2color(r: u32, g: u32, b: u32, a: u32) -> u32 {
     color
 }
 
+fn mix_color(c0: u32, c1: u32, t: f32) -> u32 {
+    let mix = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t).round() as u32;
+    rgba2color(
+        mix(red_part(c0), red_part(c1)),
+        mix(green_part(c0), green_part(c1)),
+        mix(blue_part(c0), blue_part(c1)),
+        mix(alpha_part(c0), alpha_part(c1)),
+    )
+}
+
 fn mix_color3(c0: u32, c1: u32, c2: u32, t0: f32, t1: f32, t2: f32) -> u32 {
     let r0 = red_part(c0) as f32;
     let g0 = green_part(c0) as f32;
@@ -1630,17 +3734,21 @@ fn mix_color3(c0: u32, c1: u32, c2: u32, t0: f32, t1: f32, t2: f32) -> u32 {
     rgba2color(r, g, b, a)
 }
 
-fn aa_color<const BLENDING_ENABLED: bool>(count: u8, color: u32) -> u32 {
+fn aa_color<const BLENDING_ENABLED: bool>(t: f32, color: u32) -> u32 {
     let old_alpha = if BLENDING_ENABLED {
         color >> (8 * 3) & 0xff
     } else {
         255
     } as f32;
-    let t = count as f32 / (AA_RES * AA_RES) as f32;
     let alpha = (t * old_alpha) as u32;
     color & 0x00ffffff | (alpha << (8 * 3))
 }
 
+#[inline]
+fn fract(x: f32) -> f32 {
+    x - x.floor()
+}
+
 /// ```
 /// if let Some((x0, y0), (x1, y1)) = normalize_rect(x, y, w, h, bound_width, bound_height) {
 ///     for y in y0..=y1 {
@@ -1668,10 +3776,10 @@ fn normalize_rect(
     let mut x1 = x1.clamp(0, bound_width as i32 - 1);
     let mut y1 = y1.clamp(0, bound_height as i32 - 1);
     if x1 < x0 {
-        std::mem::swap(&mut x0, &mut x1);
+        core::mem::swap(&mut x0, &mut x1);
     }
     if y1 < y0 {
-        std::mem::swap(&mut y0, &mut y1);
+        core::mem::swap(&mut y0, &mut y1);
     }
     Some(((x0 as u32, y0 as u32), (x1 as u32, y1 as u32)))
 }
@@ -1685,28 +3793,61 @@ fn triangle_bunding_box(
     mut y2: i32,
 ) -> ((i32, i32), (i32, i32)) {
     if x0 > x1 {
-        std::mem::swap(&mut x0, &mut x1);
+        core::mem::swap(&mut x0, &mut x1);
     }
     if x1 > x2 {
-        std::mem::swap(&mut x1, &mut x2);
+        core::mem::swap(&mut x1, &mut x2);
     }
     if x0 > x1 {
-        std::mem::swap(&mut x0, &mut x1);
+        core::mem::swap(&mut x0, &mut x1);
     }
 
     if y0 > y1 {
-        std::mem::swap(&mut y0, &mut y1);
+        core::mem::swap(&mut y0, &mut y1);
     }
     if y1 > y2 {
-        std::mem::swap(&mut y1, &mut y2);
+        core::mem::swap(&mut y1, &mut y2);
     }
     if y0 > y1 {
-        std::mem::swap(&mut y0, &mut y1);
+        core::mem::swap(&mut y0, &mut y1);
     }
 
     ((x0, y0), (x2, y2))
 }
 
+#[inline]
+fn xy_in_rounded_rect(
+    x: f32,
+    y: f32,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    [top_left, top_right, bottom_right, bottom_left]: [f32; 4],
+) -> bool {
+    if x < x0 || x > x1 || y < y0 || y > y1 {
+        return false;
+    }
+    let in_corner = |center_x: f32, center_y: f32, r: f32| {
+        let dx = x - center_x;
+        let dy = y - center_y;
+        dx * dx + dy * dy <= r * r
+    };
+    if x < x0 + top_left && y < y0 + top_left {
+        return in_corner(x0 + top_left, y0 + top_left, top_left);
+    }
+    if x > x1 - top_right && y < y0 + top_right {
+        return in_corner(x1 - top_right, y0 + top_right, top_right);
+    }
+    if x > x1 - bottom_right && y > y1 - bottom_right {
+        return in_corner(x1 - bottom_right, y1 - bottom_right, bottom_right);
+    }
+    if x < x0 + bottom_left && y > y1 - bottom_left {
+        return in_corner(x0 + bottom_left, y1 - bottom_left, bottom_left);
+    }
+    true
+}
+
 #[inline]
 fn xy_in_triangle(x: f32, y: f32, x0: f32, y0: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> bool {
     let (u, v, w) = barycentric(x, y, x0, y0, x1, y1, x2, y2);
@@ -1742,3 +3883,41 @@ fn barycentric(
     let u = 1.0 - w - v;
     (u, v, w)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_arc_flattens_to_few_points() {
+        let mut path = Path::new();
+        path.arc(0.0, 0.0, 1e7, 0.0, core::f32::consts::TAU);
+        let contours = path.flatten(FLATTEN_TOLERANCE);
+        assert_eq!(contours.len(), 1);
+        assert!(contours[0].0.len() <= MAX_ARC_SEGMENTS + 1);
+    }
+
+    #[test]
+    fn circle_rows_match_midpoint_circle() {
+        for r in 0..2000 {
+            let mut half_widths = vec![0; r as usize + 1];
+            let (mut x, mut y, mut d) = (0, r, 3 - 2 * r);
+            while y >= x {
+                half_widths[x as usize] = half_widths[x as usize].max(y);
+                half_widths[y as usize] = half_widths[y as usize].max(x);
+                if d < 0 {
+                    d += 4 * x + 6;
+                } else {
+                    d += 4 * (x - y) + 10;
+                    y -= 1;
+                }
+                x += 1;
+            }
+            assert_eq!(
+                CircleRows::new(r).collect::<Vec<_>>(),
+                half_widths,
+                "r = {r}"
+            );
+        }
+    }
+}
@@ -0,0 +1,302 @@
+// terminal output with ANSI escape codes, every character cell shows two pixels
+// with an upper half block, the top one as foreground and the bottom one as background,
+// and full resolution output for terminals with Sixel or Kitty graphics support
+use std::io::{self, Write};
+
+use crate::image::{gif::quantize, zlib};
+use crate::renderer::{blue_part, green_part, red_part, Renderer};
+
+const UPPER_HALF_BLOCK: &str = "\u{2580}";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermColors {
+    // 24 bit colors, `\x1b[38;2;r;g;bm`
+    TrueColor,
+    // the 6x6x6 color cube and the gray ramp of 256 color terminals, `\x1b[38;5;nm`
+    Ansi256,
+}
+impl TermColors {
+    // true color when $COLORTERM says the terminal supports it, 256 colors otherwise
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor" | "24bit") => TermColors::TrueColor,
+            _ => TermColors::Ansi256,
+        }
+    }
+}
+
+// the canvas is scaled to cols x lines cells, so cols x 2 * lines pixels,
+// every line ends with a color reset and a new line
+pub fn write_half_blocks(
+    writer: impl Write,
+    canvas: &Renderer,
+    cols: u32,
+    lines: u32,
+    colors: TermColors,
+) -> io::Result<()> {
+    write_half_blocks_rows(
+        writer,
+        canvas.width,
+        canvas.height,
+        canvas.rows(),
+        cols,
+        lines,
+        colors,
+    )
+}
+
+// rows are width pixels long, in the 0xAABBGGRR layout, alpha is ignored
+pub fn write_half_blocks_rows<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+    cols: u32,
+    lines: u32,
+    colors: TermColors,
+) -> io::Result<()> {
+    // a terminal with no room left shows nothing
+    if cols == 0 || lines == 0 {
+        return Ok(());
+    }
+    let pixels = downsample(width, height, rows, cols, lines * 2);
+    let cols = cols as usize;
+    let mut out = String::new();
+    for cells in pixels.chunks_exact(cols * 2) {
+        let (top, bottom) = cells.split_at(cols);
+        // colors are only sent when they change from the previous cell
+        let mut last = None;
+        for (&fg, &bg) in top.iter().zip(bottom) {
+            if last != Some((fg, bg)) {
+                push_color(&mut out, 38, fg, colors);
+                push_color(&mut out, 48, bg, colors);
+                last = Some((fg, bg));
+            }
+            out.push_str(UPPER_HALF_BLOCK);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    writer.write_all(out.as_bytes())?;
+    writer.flush()
+}
+
+// 38 selects the foreground, 48 the background
+fn push_color(out: &mut String, layer: u8, [r, g, b]: [u8; 3], colors: TermColors) {
+    use std::fmt::Write;
+    match colors {
+        TermColors::TrueColor => write!(out, "\x1b[{layer};2;{r};{g};{b}m"),
+        TermColors::Ansi256 => write!(out, "\x1b[{layer};5;{}m", ansi256(r, g, b)),
+    }
+    .unwrap();
+}
+
+// the closest of the color cube entries and the gray ramp entries
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const CUBE_LEVELS: [i32; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] - c as i32).abs())
+            .unwrap()
+    };
+    let distance = |[r0, g0, b0]: [i32; 3]| {
+        let (dr, dg, db) = (r0 - r as i32, g0 - g as i32, b0 - b as i32);
+        dr * dr + dg * dg + db * db
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = [CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]];
+    // the gray ramp goes from 8 to 238 in steps of 10
+    let average = (r as i32 + g as i32 + b as i32) / 3;
+    let gray_index = ((average - 8 + 5) / 10).clamp(0, 23);
+    let gray = 8 + gray_index * 10;
+    if distance([gray; 3]) < distance(cube) {
+        232 + gray_index as u8
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+// every output pixel is the average of the source pixels it covers
+fn downsample<'a>(
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+    out_width: u32,
+    out_height: u32,
+) -> Vec<[u8; 3]> {
+    let rows: Vec<&[u32]> = rows.take(height as usize).collect();
+    // the source span of output pixel i, at least one pixel wide
+    let span = |i: u32, len: u32, out_len: u32| {
+        let start = (i as u64 * len as u64 / out_len as u64) as usize;
+        let end = ((i as u64 + 1) * len as u64 / out_len as u64) as usize;
+        start.min(len as usize - 1)..end.max(start + 1).min(len as usize)
+    };
+    let mut pixels = Vec::with_capacity(out_width as usize * out_height as usize);
+    if width == 0 || height == 0 {
+        pixels.resize(out_width as usize * out_height as usize, [0; 3]);
+        return pixels;
+    }
+    for oy in 0..out_height {
+        let ys = span(oy, height, out_height);
+        for ox in 0..out_width {
+            let xs = span(ox, width, out_width);
+            let mut sum = [0u64; 3];
+            for row in &rows[ys.clone()] {
+                for &pixel in &row[xs.clone()] {
+                    sum[0] += red_part(pixel) as u64;
+                    sum[1] += green_part(pixel) as u64;
+                    sum[2] += blue_part(pixel) as u64;
+                }
+            }
+            let count = (ys.len() * xs.len()) as u64;
+            pixels.push(sum.map(|s| ((s + count / 2) / count) as u8));
+        }
+    }
+    pixels
+}
+
+// a DCS sequence drawing the canvas at the cursor, with a palette of at most 256 colors
+pub fn write_sixel(writer: impl Write, canvas: &Renderer) -> io::Result<()> {
+    write_sixel_rows(writer, canvas.width, canvas.height, canvas.rows())
+}
+
+// rows are width pixels long, in the 0xAABBGGRR layout, alpha is ignored
+pub fn write_sixel_rows<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]> + Clone,
+) -> io::Result<()> {
+    use std::fmt::Write;
+    // an empty image has no bands to draw
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    let (width, height) = (width as usize, height as usize);
+    let rows = rows.take(height).map(|row| &row[..width]);
+    let (palette, indices) = quantize(rows);
+
+    // square pixels and the raster size
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    // color components are percentages
+    for (i, [r, g, b]) in palette.iter().enumerate() {
+        let [r, g, b] = [r, g, b].map(|&c| (c as u32 * 100 + 127) / 255);
+        write!(out, "#{i};2;{r};{g};{b}").unwrap();
+    }
+    // every band is 6 pixels high, each color in it is drawn in its own pass over the band
+    let mut used = vec![false; palette.len()];
+    let mut sixels = vec![0u8; width];
+    for (band, band_indices) in indices.chunks(width * 6).enumerate() {
+        if band > 0 {
+            out.push('-');
+        }
+        used.fill(false);
+        band_indices.iter().for_each(|&i| used[i as usize] = true);
+        let mut first = true;
+        for color in (0..palette.len()).filter(|&color| used[color]) {
+            sixels.fill(0);
+            for (y, row) in band_indices.chunks_exact(width).enumerate() {
+                for (sixel, &i) in sixels.iter_mut().zip(row) {
+                    if i as usize == color {
+                        *sixel |= 1 << y;
+                    }
+                }
+            }
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            write!(out, "#{color}").unwrap();
+            // runs of the same sixel are written as !count
+            let mut x = 0;
+            while x < width {
+                let run = sixels[x..].iter().take_while(|&&s| s == sixels[x]).count();
+                let c = (0x3f + sixels[x]) as char;
+                if run > 3 {
+                    write!(out, "!{run}{c}").unwrap();
+                } else {
+                    (0..run).for_each(|_| out.push(c));
+                }
+                x += run;
+            }
+        }
+    }
+    out.push_str("\x1b\\");
+    writer.write_all(out.as_bytes())?;
+    writer.flush()
+}
+
+// an APC sequence transmitting the canvas as RGBA, zlib compressed, and displaying it at the cursor,
+// with an id the previous image with the same id is replaced instead of drawing another one
+pub fn write_kitty(writer: impl Write, canvas: &Renderer, id: Option<u32>) -> io::Result<()> {
+    write_kitty_rows(writer, canvas.width, canvas.height, canvas.rows(), id)
+}
+
+// rows are width pixels long, in the 0xAABBGGRR layout
+pub fn write_kitty_rows<'a>(
+    mut writer: impl Write,
+    width: u32,
+    height: u32,
+    rows: impl Iterator<Item = &'a [u32]>,
+    id: Option<u32>,
+) -> io::Result<()> {
+    // terminals reject images without pixels
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    // 0xAABBGGRR is already R, G, B, A in little endian
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in rows.take(height as usize) {
+        for &pixel in &row[..width as usize] {
+            rgba.extend_from_slice(&pixel.to_le_bytes());
+        }
+    }
+    // noisy images can grow when compressed
+    let compressed = zlib::compress(&rgba);
+    let (data, compression) = if compressed.len() < rgba.len() {
+        (base64(&compressed), ",o=z")
+    } else {
+        (base64(&rgba), "")
+    };
+
+    // the payload is sent in chunks of at most 4096 bytes, m=1 while more chunks follow,
+    // q=2 keeps the terminal from answering
+    let mut control = format!("a=T,f=32{compression},s={width},v={height},q=2");
+    if let Some(id) = id {
+        control.push_str(&format!(",i={id},p=1"));
+    }
+    let mut chunks = data.as_bytes().chunks(4096).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = chunks.peek().is_some() as u8;
+        if first {
+            write!(writer, "\x1b_G{control},m={more};")?;
+            first = false;
+        } else {
+            write!(writer, "\x1b_Gm={more};")?;
+        }
+        writer.write_all(chunk)?;
+        writer.write_all(b"\x1b\\")?;
+    }
+    writer.flush()
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
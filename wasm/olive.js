@@ -0,0 +1,24 @@
+// runs an example built with
+//   cargo build --release --example <name> --target wasm32-unknown-unknown --no-default-features --features wasm
+// on a canvas, the module exports width, height, init and render(dt) returning a pointer to the pixels
+export async function run(canvas, url) {
+    const { instance } = await WebAssembly.instantiateStreaming(fetch(url), {});
+    const { memory, width, height, init, render } = instance.exports;
+    const w = width();
+    const h = height();
+    canvas.width = w;
+    canvas.height = h;
+    const context = canvas.getContext("2d");
+    init();
+    let previous;
+    const frame = (timestamp) => {
+        const dt = previous === undefined ? 0 : (timestamp - previous) / 1000;
+        previous = timestamp;
+        const pointer = render(dt);
+        // the view is made every frame, memory.buffer changes when the memory grows
+        const pixels = new Uint8ClampedArray(memory.buffer, pointer, w * h * 4);
+        context.putImageData(new ImageData(pixels, w, h), 0, 0);
+        requestAnimationFrame(frame);
+    };
+    requestAnimationFrame(frame);
+}